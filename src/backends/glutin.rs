@@ -1,6 +1,9 @@
 use crate::input::*;
 use crate::gouache::{*, renderers::GlRenderer};
 use crate::*;
+use crate::backends::{Clipboard, MemoryClipboard, handle_clipboard};
+
+use ::clipboard::{ClipboardContext, ClipboardProvider};
 
 const FRAME: std::time::Duration = std::time::Duration::from_micros(1_000_000 / 60);
 
@@ -20,15 +23,22 @@ pub fn run<E: Elem, F: FnMut() -> E>(mut template: F) {
     let mut renderer = GlRenderer::new();
 
     let mut input_state = InputState::default();
+    let mut clipboard: Box<dyn Clipboard> = match SystemClipboard::new() {
+        Some(clipboard) => Box::new(clipboard),
+        None => Box::new(MemoryClipboard::new()),
+    };
 
     let mut root = Node::new();
+    let mut dispatcher = Dispatcher::new();
 
     let mut running = true;
     let mut now = std::time::Instant::now();
     while running {
         let size = context.window().get_inner_size().unwrap();
 
-        template().apply(&mut root, Bounds::new(size.width as f32, size.height as f32));
+        template().apply(&mut root, Constraints::tight(size.width as f32, size.height as f32));
+        root.update_focus();
+        dispatcher.update_hover(&mut root, &input_state);
 
         let mut frame = Frame::new(&mut cache, &mut renderer, size.width as f32, size.height as f32);
 
@@ -62,12 +72,34 @@ pub fn run<E: Elem, F: FnMut() -> E>(mut template: F) {
             }
 
             if let Some(input) = process_event(event, &mut input_state) {
-                root.input(input, &input_state);
+                if !handle_clipboard(&mut root, input, &input_state, &mut *clipboard) {
+                    dispatcher.input(&mut root, input, &input_state);
+                }
             }
         });
     }
 }
 
+pub struct SystemClipboard {
+    context: ClipboardContext,
+}
+
+impl SystemClipboard {
+    pub fn new() -> Option<SystemClipboard> {
+        ClipboardContext::new().ok().map(|context| SystemClipboard { context })
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        self.context.get_contents().ok()
+    }
+
+    fn set_text(&mut self, text: String) {
+        let _ = self.context.set_contents(text);
+    }
+}
+
 pub fn process_event(event: glutin::Event, input_state: &mut InputState) -> Option<Input> {
     match event {
         glutin::Event::WindowEvent { event, .. } => {
@@ -78,6 +110,9 @@ pub fn process_event(event: glutin::Event, input_state: &mut InputState) -> Opti
                     input_state.mouse_y = position.y as f32;
                     Some(Input::MouseMove)
                 }
+                CursorLeft { .. } => {
+                    Some(Input::MouseLeave)
+                }
                 MouseInput { state, button, modifiers, .. } => {
                     input_state.modifiers = glutin_modifiers(modifiers);
                     match button {
@@ -1 +1,66 @@
 pub mod glutin;
+
+use crate::input::{Input, InputState, Key};
+use crate::Node;
+
+pub trait Clipboard {
+    fn get_text(&mut self) -> Option<String>;
+    fn set_text(&mut self, text: String);
+}
+
+pub struct MemoryClipboard {
+    text: Option<String>,
+}
+
+impl MemoryClipboard {
+    pub fn new() -> MemoryClipboard {
+        MemoryClipboard { text: None }
+    }
+}
+
+impl Clipboard for MemoryClipboard {
+    fn get_text(&mut self) -> Option<String> {
+        self.text.clone()
+    }
+
+    fn set_text(&mut self, text: String) {
+        self.text = Some(text);
+    }
+}
+
+/// Performs copy, cut or paste on the focused node if `input` is the corresponding ctrl+C,
+/// ctrl+X or ctrl+V shortcut, or cmd+C, cmd+X or cmd+V on macOS. Returns `true` if the shortcut
+/// was handled, in which case the event should not also be passed to `Dispatcher::input`.
+pub fn handle_clipboard(root: &mut Node, input: Input, input_state: &InputState, clipboard: &mut dyn Clipboard) -> bool {
+    let modifiers = input_state.modifiers;
+    if !(modifiers.ctrl || modifiers.meta) {
+        return false;
+    }
+
+    match input {
+        Input::KeyDown(Key::C) => {
+            if let Some(text) = root.copy() {
+                clipboard.set_text(text);
+                true
+            } else {
+                false
+            }
+        }
+        Input::KeyDown(Key::X) => {
+            if let Some(text) = root.cut(input_state) {
+                clipboard.set_text(text);
+                true
+            } else {
+                false
+            }
+        }
+        Input::KeyDown(Key::V) => {
+            if let Some(text) = clipboard.get_text() {
+                root.paste(&text, input_state)
+            } else {
+                false
+            }
+        }
+        _ => false,
+    }
+}
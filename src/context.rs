@@ -0,0 +1,109 @@
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::*;
+
+thread_local! {
+    static CONTEXT: RefCell<HashMap<TypeId, Vec<Rc<dyn Any>>>> = RefCell::new(HashMap::new());
+}
+
+/// Returns the value of type `T` provided by the innermost enclosing `Provide` element, or
+/// `None` if no ancestor of the element currently being applied provides one.
+pub fn context<T: 'static>() -> Option<Rc<T>> {
+    CONTEXT.with(|context| {
+        context.borrow()
+            .get(&TypeId::of::<T>())
+            .and_then(|values| values.last())
+            .and_then(|value| value.clone().downcast::<T>().ok())
+    })
+}
+
+/// Makes `value` available to every element inside `child` through `context::<T>()`. A
+/// `Provide` for the same type further down the tree shadows this one for its own subtree.
+pub struct Provide<T: 'static, C: Elem> {
+    value: Rc<T>,
+    child: C,
+}
+
+impl<T: 'static, C: Elem> Provide<T, C> {
+    pub fn new(value: T, child: C) -> Provide<T, C> {
+        Provide { value: Rc::new(value), child }
+    }
+}
+
+/// Removes a provided value when dropped, so that the context is restored even if applying
+/// the subtree panics.
+struct ProvideGuard(TypeId);
+
+impl Drop for ProvideGuard {
+    fn drop(&mut self) {
+        CONTEXT.with(|context| {
+            if let Some(values) = context.borrow_mut().get_mut(&self.0) {
+                values.pop();
+            }
+        });
+    }
+}
+
+impl<T: 'static, C: Elem> Elem for Provide<T, C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
+
+        CONTEXT.with(|context| {
+            context.borrow_mut().entry(TypeId::of::<T>()).or_insert_with(Vec::new).push(self.value);
+        });
+        let guard = ProvideGuard(TypeId::of::<T>());
+        self.child.apply(node.edit_children().add(), constraints);
+        drop(guard);
+
+        let (width, height) = node.children()[0].size();
+        let baseline = node.children()[0].baseline();
+        node.set_size(width, height);
+        node.set_baseline(baseline);
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.child.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.child.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.child.stack_placement()
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.child.key()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Read(Rc<RefCell<Vec<Option<u32>>>>);
+
+    impl Elem for Read {
+        fn apply(self, node: &mut Node, constraints: Constraints) {
+            self.0.borrow_mut().push(context::<u32>().map(|value| *value));
+        }
+    }
+
+    #[test]
+    fn inner_provide_shadows_outer() {
+        let seen = Rc::new(RefCell::new(Vec::new()));
+        let tree = Provide::new(1u32, Col::new(0.0, (
+            Read(seen.clone()),
+            Provide::new(2u32, Read(seen.clone())),
+            Read(seen.clone()),
+        )));
+        tree.apply(&mut Node::new(), Constraints::unbounded());
+
+        assert_eq!(*seen.borrow(), vec![Some(1), Some(2), Some(1)]);
+        assert!(context::<u32>().is_none());
+    }
+}
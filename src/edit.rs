@@ -0,0 +1,408 @@
+use std::ops::Range;
+
+use gouache::{Font, Glyph};
+
+use crate::input::Modifiers;
+
+pub struct EditBuffer {
+    text: String,
+    cursor: usize,
+    anchor: usize,
+}
+
+impl EditBuffer {
+    pub fn new(text: String) -> EditBuffer {
+        let len = text.len();
+        EditBuffer { text, cursor: len, anchor: len }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Replaces the text, keeping the cursor and selection in place as far as the new text
+    /// allows.
+    pub fn set_text(&mut self, text: String) {
+        self.text = text;
+        self.cursor = self.clamp(self.cursor);
+        self.anchor = self.clamp(self.anchor);
+    }
+
+    fn clamp(&self, index: usize) -> usize {
+        let mut index = index.min(self.text.len());
+        while !self.text.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    }
+
+    pub fn cursor(&self) -> usize {
+        self.cursor
+    }
+
+    pub fn selection(&self) -> Range<usize> {
+        self.cursor.min(self.anchor)..self.cursor.max(self.anchor)
+    }
+
+    pub fn has_selection(&self) -> bool {
+        self.cursor != self.anchor
+    }
+
+    pub fn selected_text(&self) -> &str {
+        &self.text[self.selection()]
+    }
+
+    pub fn set_cursor(&mut self, index: usize, extend: bool) {
+        self.cursor = index.min(self.text.len());
+        if !extend {
+            self.anchor = self.cursor;
+        }
+    }
+
+    pub fn select_all(&mut self) {
+        self.anchor = 0;
+        self.cursor = self.text.len();
+    }
+
+    pub fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+        self.anchor = self.cursor;
+    }
+
+    pub fn delete_selection(&mut self) -> bool {
+        if !self.has_selection() {
+            return false;
+        }
+
+        let selection = self.selection();
+        self.text.replace_range(selection.clone(), "");
+        self.cursor = selection.start;
+        self.anchor = selection.start;
+        true
+    }
+
+    pub fn delete_backward(&mut self) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+
+        let start = self.prev_boundary(self.cursor);
+        if start == self.cursor {
+            return false;
+        }
+        self.text.replace_range(start..self.cursor, "");
+        self.cursor = start;
+        self.anchor = start;
+        true
+    }
+
+    pub fn delete_forward(&mut self) -> bool {
+        if self.delete_selection() {
+            return true;
+        }
+
+        let end = self.next_boundary(self.cursor);
+        if end == self.cursor {
+            return false;
+        }
+        self.text.replace_range(self.cursor..end, "");
+        true
+    }
+
+    pub fn move_left(&mut self, extend: bool) {
+        if self.has_selection() && !extend {
+            let start = self.selection().start;
+            self.set_cursor(start, false);
+        } else {
+            let index = self.prev_boundary(self.cursor);
+            self.set_cursor(index, extend);
+        }
+    }
+
+    pub fn move_right(&mut self, extend: bool) {
+        if self.has_selection() && !extend {
+            let end = self.selection().end;
+            self.set_cursor(end, false);
+        } else {
+            let index = self.next_boundary(self.cursor);
+            self.set_cursor(index, extend);
+        }
+    }
+
+    pub fn move_word_left(&mut self, extend: bool) {
+        let index = self.prev_word_boundary(self.cursor);
+        self.set_cursor(index, extend);
+    }
+
+    pub fn move_word_right(&mut self, extend: bool) {
+        let index = self.next_word_boundary(self.cursor);
+        self.set_cursor(index, extend);
+    }
+
+    pub fn prev_word_boundary(&self, index: usize) -> usize {
+        let mut chars = self.text[..index].char_indices().rev().peekable();
+        while let Some(&(_, c)) = chars.peek() {
+            if is_word_char(c) { break; }
+            chars.next();
+        }
+        let mut boundary = chars.peek().map_or(0, |&(i, _)| i);
+        while let Some(&(i, c)) = chars.peek() {
+            if !is_word_char(c) { break; }
+            boundary = i;
+            chars.next();
+        }
+        boundary
+    }
+
+    pub fn next_word_boundary(&self, index: usize) -> usize {
+        let mut chars = self.text[index..].char_indices().peekable();
+        while let Some(&(_, c)) = chars.peek() {
+            if is_word_char(c) { break; }
+            chars.next();
+        }
+        while let Some(&(_, c)) = chars.peek() {
+            if !is_word_char(c) { break; }
+            chars.next();
+        }
+        chars.peek().map_or(self.text.len(), |&(i, _)| index + i)
+    }
+
+    pub fn prev_boundary(&self, index: usize) -> usize {
+        self.text[..index].chars().next_back().map_or(index, |c| index - c.len_utf8())
+    }
+
+    pub fn next_boundary(&self, index: usize) -> usize {
+        self.text[index..].chars().next().map_or(index, |c| index + c.len_utf8())
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether a text field inserts `c` when it is typed, as opposed to control characters and
+/// characters typed as part of a shortcut.
+pub fn accepts_char(c: char, modifiers: Modifiers) -> bool {
+    !c.is_control() && !modifiers.ctrl && !modifiers.meta
+}
+
+/// Converts Windows (`\r\n`) and classic Mac (`\r`) line endings to `\n`, e.g. for pasted text.
+pub fn normalize_newlines(text: &str) -> String {
+    text.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+pub fn line_height(font: &Font<'static>, size: f32) -> f32 {
+    font.measure(" ", size).1
+}
+
+/// Returns the distance from the top of a line of text to its baseline.
+pub fn baseline(font: &Font<'static>, size: f32) -> f32 {
+    font.layout("x", size).first().map_or(line_height(font, size), |glyph| glyph.position.y)
+}
+
+/// Breaks `text` into lines no wider than `width`, returning the byte range of each line.
+/// Lines are broken at explicit newlines (which are excluded from the ranges) and after
+/// whitespace; a single word wider than `width` is broken between characters.
+pub fn wrap_lines(font: &Font<'static>, size: f32, text: &str, width: f32) -> Vec<Range<usize>> {
+    let mut lines = Vec::new();
+
+    let mut paragraph_start = 0;
+    for paragraph in text.split('\n') {
+        let offsets = caret_offsets(font, size, paragraph);
+
+        let mut line_start = 0;
+        let mut line_x = 0.0;
+        let mut line_break = None;
+        for (i, &(index, x)) in offsets.iter().enumerate().skip(1) {
+            let prev = offsets[i - 1].0;
+            if x - line_x > width && prev > line_start {
+                let end = match line_break {
+                    Some(line_break) if line_break > line_start => line_break,
+                    _ => prev,
+                };
+                lines.push(paragraph_start + line_start..paragraph_start + end);
+                line_start = end;
+                line_x = caret_x(&offsets, end);
+            }
+            if paragraph[prev..index].chars().all(char::is_whitespace) {
+                line_break = Some(index);
+            }
+        }
+        lines.push(paragraph_start + line_start..paragraph_start + paragraph.len());
+
+        paragraph_start += paragraph.len() + 1;
+    }
+
+    lines
+}
+
+/// Returns the horizontal position of the caret before each character of `text`, paired with
+/// that character's byte index, followed by the position at the end of the text.
+pub fn caret_offsets(font: &Font<'static>, size: f32, text: &str) -> Vec<(usize, f32)> {
+    let glyphs = font.layout(text, size);
+    let (width, _) = font.measure(text, size);
+
+    let mut offsets = Vec::with_capacity(glyphs.len() + 1);
+    if glyphs.len() == text.chars().count() {
+        for ((index, _), glyph) in text.char_indices().zip(glyphs.iter()) {
+            offsets.push((index, glyph.position.x));
+        }
+    } else {
+        for (index, _) in text.char_indices() {
+            offsets.push((index, font.measure(&text[..index], size).0));
+        }
+    }
+    offsets.push((text.len(), width));
+
+    offsets
+}
+
+/// Returns the advance of each glyph laid out from a single line of text that is `width` wide:
+/// the distance to the next glyph, or to the end of the line for the last one.
+pub fn advances(glyphs: &[Glyph], width: f32) -> Vec<f32> {
+    let mut advances: Vec<f32> = glyphs.windows(2).map(|pair| pair[1].position.x - pair[0].position.x).collect();
+    if let Some(last) = glyphs.last() {
+        advances.push(width - last.position.x);
+    }
+    advances
+}
+
+/// Returns the byte index of the caret position closest to `x`.
+pub fn hit_test(offsets: &[(usize, f32)], x: f32) -> usize {
+    let mut closest = (0, std::f32::INFINITY);
+    for &(index, offset) in offsets {
+        let distance = (offset - x).abs();
+        if distance < closest.1 {
+            closest = (index, distance);
+        }
+    }
+    closest.0
+}
+
+pub fn caret_x(offsets: &[(usize, f32)], index: usize) -> f32 {
+    offsets.iter().find(|&&(i, _)| i == index).map_or(0.0, |&(_, x)| x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Theme;
+
+    #[test]
+    fn insert_replaces_selection() {
+        let mut buffer = EditBuffer::new("hello world".to_string());
+        buffer.set_cursor(0, false);
+        buffer.set_cursor(5, true);
+        assert_eq!(buffer.selected_text(), "hello");
+
+        buffer.insert("goodbye");
+        assert_eq!(buffer.text(), "goodbye world");
+        assert_eq!(buffer.cursor(), 7);
+        assert!(!buffer.has_selection());
+    }
+
+    #[test]
+    fn delete_respects_char_boundaries() {
+        let mut buffer = EditBuffer::new("h\u{e9}llo".to_string());
+        buffer.set_cursor(3, false);
+        assert!(buffer.delete_backward());
+        assert_eq!(buffer.text(), "hllo");
+        assert_eq!(buffer.cursor(), 1);
+
+        assert!(buffer.delete_forward());
+        assert_eq!(buffer.text(), "hlo");
+
+        buffer.set_cursor(0, false);
+        assert!(!buffer.delete_backward());
+    }
+
+    #[test]
+    fn set_text_clamps_cursor() {
+        let mut buffer = EditBuffer::new("hello".to_string());
+        buffer.set_text("hi".to_string());
+        assert_eq!(buffer.cursor(), 2);
+
+        buffer.set_cursor(1, false);
+        buffer.set_text("\u{e9}".to_string());
+        assert_eq!(buffer.cursor(), 0);
+    }
+
+    #[test]
+    fn hit_test_picks_closest_offset() {
+        let offsets = [(0, 0.0), (1, 10.0), (3, 20.0)];
+        assert_eq!(hit_test(&offsets, -5.0), 0);
+        assert_eq!(hit_test(&offsets, 4.0), 0);
+        assert_eq!(hit_test(&offsets, 14.0), 1);
+        assert_eq!(hit_test(&offsets, 16.0), 3);
+        assert_eq!(hit_test(&offsets, 100.0), 3);
+    }
+
+    #[test]
+    fn caret_offsets_cover_text() {
+        let font = Theme::default_font();
+        let text = "h\u{e9}llo world";
+        let offsets = caret_offsets(&font, 14.0, text);
+
+        let indices: Vec<usize> = offsets.iter().map(|&(index, _)| index).collect();
+        let mut expected: Vec<usize> = text.char_indices().map(|(index, _)| index).collect();
+        expected.push(text.len());
+        assert_eq!(indices, expected);
+
+        assert!(offsets.windows(2).all(|pair| pair[0].1 <= pair[1].1));
+        assert_eq!(offsets.last().unwrap().1, font.measure(text, 14.0).0);
+    }
+
+    #[test]
+    fn word_movement() {
+        let mut buffer = EditBuffer::new("foo bar_baz  qux".to_string());
+        buffer.set_cursor(0, false);
+        buffer.move_word_right(false);
+        assert_eq!(buffer.cursor(), 3);
+        buffer.move_word_right(false);
+        assert_eq!(buffer.cursor(), 11);
+        buffer.move_word_right(true);
+        assert_eq!(buffer.cursor(), 16);
+        assert_eq!(buffer.selected_text(), "  qux");
+
+        buffer.move_word_left(false);
+        assert_eq!(buffer.cursor(), 13);
+        buffer.move_word_left(false);
+        assert_eq!(buffer.cursor(), 4);
+    }
+
+    #[test]
+    fn wrap_lines_fit_width() {
+        let font = Theme::default_font();
+        let text = "the quick brown fox\njumps over the lazy dog";
+        let width = font.measure("the quick ", 14.0).0;
+        let lines = wrap_lines(&font, 14.0, text, width);
+
+        assert!(lines.len() > 2);
+        for line in lines.iter() {
+            assert!(font.measure(text[line.clone()].trim_end(), 14.0).0 <= width + 0.01);
+        }
+
+        // The lines cover the text, except for the newline between the paragraphs.
+        let joined: String = lines.iter().map(|line| &text[line.clone()]).collect();
+        assert_eq!(joined, text.replace('\n', ""));
+    }
+
+    #[test]
+    fn wrap_lines_breaks_long_words() {
+        let font = Theme::default_font();
+        let text = "abcdefghijklmnop";
+        let width = font.measure("abcd", 14.0).0;
+        let lines = wrap_lines(&font, 14.0, text, width);
+
+        assert!(lines.len() > 1);
+        assert!(lines.iter().all(|line| !line.is_empty()));
+        assert_eq!(lines.last().unwrap().end, text.len());
+    }
+
+    #[test]
+    fn normalize_line_endings() {
+        assert_eq!(normalize_newlines("a\r\nb\rc\n"), "a\nb\nc\n");
+    }
+}
@@ -0,0 +1,335 @@
+use crate::*;
+
+/// How a child of a `Row` or `Col` is sized along the main axis.
+///
+/// Children with a `grow` and `shrink` of zero are laid out at their natural size. The rest start
+/// at `basis` and split the remaining space: positive free space in proportion to `grow`,
+/// negative free space in proportion to `shrink * basis`. The result is clamped to `min..max`.
+/// When the main axis is unbounded, flexible children are laid out at their natural size within
+/// `min..max` as well.
+#[derive(Copy, Clone)]
+pub struct FlexParams {
+    pub grow: f32,
+    pub shrink: f32,
+    pub basis: f32,
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for FlexParams {
+    fn default() -> FlexParams {
+        FlexParams {
+            grow: 0.0,
+            shrink: 0.0,
+            basis: 0.0,
+            min: 0.0,
+            max: std::f32::INFINITY,
+        }
+    }
+}
+
+impl FlexParams {
+    fn is_rigid(&self) -> bool {
+        self.grow == 0.0 && self.shrink == 0.0
+    }
+
+    fn clamp(&self, size: f32) -> f32 {
+        size.min(self.max).max(self.min)
+    }
+}
+
+pub struct Flexible<C: Elem> {
+    params: FlexParams,
+    child: C,
+}
+
+impl<C: Elem> Flexible<C> {
+    pub fn new(child: C) -> Flexible<C> {
+        Flexible {
+            params: FlexParams { grow: 1.0, shrink: 1.0, ..FlexParams::default() },
+            child,
+        }
+    }
+
+    pub fn grow(mut self, grow: f32) -> Flexible<C> {
+        self.params.grow = grow;
+        self
+    }
+
+    pub fn shrink(mut self, shrink: f32) -> Flexible<C> {
+        self.params.shrink = shrink;
+        self
+    }
+
+    pub fn basis(mut self, basis: f32) -> Flexible<C> {
+        self.params.basis = basis;
+        self
+    }
+
+    pub fn min(mut self, min: f32) -> Flexible<C> {
+        self.params.min = min;
+        self
+    }
+
+    pub fn max(mut self, max: f32) -> Flexible<C> {
+        self.params.max = max;
+        self
+    }
+}
+
+impl<C: Elem> Elem for Flexible<C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        self.child.apply(node, constraints);
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.params
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.child.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.child.stack_placement()
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.child.key()
+    }
+}
+
+/// Cross-axis alignment of the children of a `Row` or `Col`.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Align {
+    Start,
+    Center,
+    End,
+    Stretch,
+    /// Aligns children by their first baseline. Only meaningful in a `Row`; a `Col` treats it
+    /// as `Start`.
+    Baseline,
+}
+
+/// Main-axis distribution of the free space in a `Row` or `Col`.
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum Justify {
+    Start,
+    Center,
+    End,
+    SpaceBetween,
+    SpaceAround,
+}
+
+/// Returns the offset of something of length `size` aligned within `available`.
+pub fn align_offset(align: Align, available: f32, size: f32) -> f32 {
+    match align {
+        Align::Center => (available - size) / 2.0,
+        Align::End => available - size,
+        Align::Start | Align::Stretch | Align::Baseline => 0.0,
+    }
+}
+
+impl Axis {
+    fn main(self, (width, height): (f32, f32)) -> f32 {
+        match self {
+            Axis::Horizontal => width,
+            Axis::Vertical => height,
+        }
+    }
+
+    fn cross(self, (width, height): (f32, f32)) -> f32 {
+        match self {
+            Axis::Horizontal => height,
+            Axis::Vertical => width,
+        }
+    }
+
+    fn pack(self, main: f32, cross: f32) -> (f32, f32) {
+        match self {
+            Axis::Horizontal => (main, cross),
+            Axis::Vertical => (cross, main),
+        }
+    }
+
+    fn constraints(self, min_main: f32, max_main: f32, min_cross: f32, max_cross: f32) -> Constraints {
+        match self {
+            Axis::Horizontal => Constraints::new(min_main, max_main, min_cross, max_cross),
+            Axis::Vertical => Constraints::new(min_cross, max_cross, min_main, max_main),
+        }
+    }
+}
+
+pub(crate) fn flex_layout(node: &mut Node, items: &mut [&mut dyn ElemItem], axis: Axis, spacing: f32, justify: Justify, align: Align, constraints: Constraints) {
+    let (min_main, max_main, min_cross, max_cross) = match axis {
+        Axis::Horizontal => (constraints.min_width(), constraints.max_width(), constraints.min_height(), constraints.max_height()),
+        Axis::Vertical => (constraints.min_height(), constraints.max_height(), constraints.min_width(), constraints.max_width()),
+    };
+    let stretch = align == Align::Stretch && max_cross.is_finite();
+    let min_child_cross = if stretch { max_cross } else { 0.0 };
+    let count = items.len();
+
+    {
+        let mut children = node.edit_children();
+        for item in items.iter() {
+            children.add_with_key(item.key());
+        }
+    }
+
+    let mut sizes = vec![0.0; count];
+    let mut used = spacing * count.saturating_sub(1) as f32;
+    let mut flexible = Vec::new();
+    for (i, item) in items.iter_mut().enumerate() {
+        let params = item.flex();
+        // Without a bound on the main axis there is no space to share, so flexible children
+        // are laid out loosely at their natural size, like rigid ones.
+        if params.is_rigid() || !max_main.is_finite() {
+            let child = &mut node.children_mut()[i];
+            item.apply(child, axis.constraints(params.min, params.max, min_child_cross, max_cross));
+            sizes[i] = axis.main(child.size());
+            used += sizes[i];
+        } else {
+            flexible.push((i, params));
+        }
+    }
+
+    let available = max_main - used;
+    let targets = resolve_flexible(&flexible.iter().map(|&(_, params)| params).collect::<Vec<_>>(), available);
+    for (&(i, _), target) in flexible.iter().zip(targets) {
+        let child = &mut node.children_mut()[i];
+        items[i].apply(child, axis.constraints(target, target, min_child_cross, max_cross));
+        sizes[i] = axis.main(child.size());
+    }
+
+    let baseline_align = align == Align::Baseline && axis == Axis::Horizontal;
+    let mut cross_size: f32 = 0.0;
+    let mut max_baseline: f32 = 0.0;
+    let mut max_descent: f32 = 0.0;
+    for child in node.children() {
+        let child_cross = axis.cross(child.size());
+        cross_size = cross_size.max(child_cross);
+        let baseline = child.baseline().unwrap_or(child_cross);
+        max_baseline = max_baseline.max(baseline);
+        max_descent = max_descent.max(child_cross - baseline);
+    }
+    if baseline_align {
+        cross_size = cross_size.max(max_baseline + max_descent);
+    }
+    let cross_size = cross_size.min(max_cross).max(min_cross);
+
+    let total = sizes.iter().sum::<f32>() + spacing * count.saturating_sub(1) as f32;
+    let main_size = if justify != Justify::Start && max_main.is_finite() {
+        max_main
+    } else {
+        total.min(max_main).max(min_main)
+    };
+    let leftover = (main_size - total).max(0.0);
+    let (mut position, gap) = match justify {
+        Justify::Start => (0.0, spacing),
+        Justify::Center => (leftover / 2.0, spacing),
+        Justify::End => (leftover, spacing),
+        Justify::SpaceBetween if count > 1 => (0.0, spacing + leftover / (count - 1) as f32),
+        Justify::SpaceBetween => (0.0, spacing),
+        Justify::SpaceAround if count > 0 => (leftover / count as f32 / 2.0, spacing + leftover / count as f32),
+        Justify::SpaceAround => (0.0, spacing),
+    };
+
+    let mut baseline = None;
+    for (i, child) in node.children_mut().iter_mut().enumerate() {
+        let child_cross = axis.cross(child.size());
+        let cross_offset = if baseline_align {
+            max_baseline - child.baseline().unwrap_or(child_cross)
+        } else {
+            align_offset(align, cross_size, child_cross)
+        };
+
+        let (x, y) = axis.pack(position, cross_offset);
+        child.set_offset(x, y);
+        if baseline.is_none() {
+            baseline = child.baseline().map(|child_baseline| y + child_baseline);
+        }
+        position += sizes[i] + gap;
+    }
+
+    let (width, height) = axis.pack(main_size, cross_size);
+    node.set_size(width, height);
+    node.set_baseline(if baseline_align { Some(max_baseline) } else { baseline });
+}
+
+/// Distributes `available` space among flexible children, freezing children whose size
+/// violates their min or max constraint and redistributing the remainder.
+fn resolve_flexible(params: &[FlexParams], available: f32) -> Vec<f32> {
+    let mut targets: Vec<f32> = params.iter().map(|params| params.basis).collect();
+    let mut frozen = vec![false; params.len()];
+
+    loop {
+        let mut free = available;
+        let mut total_grow = 0.0;
+        let mut total_shrink = 0.0;
+        for (i, params) in params.iter().enumerate() {
+            if frozen[i] {
+                free -= targets[i];
+            } else {
+                free -= params.basis;
+                total_grow += params.grow;
+                total_shrink += params.shrink * params.basis;
+            }
+        }
+
+        let mut clamped = false;
+        for (i, params) in params.iter().enumerate() {
+            if frozen[i] {
+                continue;
+            }
+
+            let mut target = params.basis;
+            if free > 0.0 && total_grow > 0.0 {
+                target += free * params.grow / total_grow;
+            } else if free < 0.0 && total_shrink > 0.0 {
+                target += free * params.shrink * params.basis / total_shrink;
+            }
+
+            targets[i] = params.clamp(target);
+            if targets[i] != target {
+                frozen[i] = true;
+                clamped = true;
+            }
+        }
+
+        if !clamped {
+            return targets;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flexible(grow: f32, shrink: f32, basis: f32) -> FlexParams {
+        FlexParams { grow, shrink, basis, ..FlexParams::default() }
+    }
+
+    #[test]
+    fn grow_in_proportion() {
+        let targets = resolve_flexible(&[flexible(1.0, 0.0, 0.0), flexible(3.0, 0.0, 0.0)], 100.0);
+        assert_eq!(targets, vec![25.0, 75.0]);
+    }
+
+    #[test]
+    fn shrink_in_proportion_to_basis() {
+        let targets = resolve_flexible(&[flexible(0.0, 1.0, 100.0), flexible(0.0, 1.0, 300.0)], 200.0);
+        assert_eq!(targets, vec![50.0, 150.0]);
+    }
+
+    #[test]
+    fn clamped_children_are_frozen() {
+        let capped = FlexParams { max: 10.0, ..flexible(1.0, 0.0, 0.0) };
+        let targets = resolve_flexible(&[capped, flexible(1.0, 0.0, 0.0)], 100.0);
+        assert_eq!(targets, vec![10.0, 90.0]);
+
+        let floored = FlexParams { min: 80.0, ..flexible(0.0, 1.0, 100.0) };
+        let targets = resolve_flexible(&[floored, flexible(0.0, 1.0, 100.0)], 100.0);
+        assert_eq!(targets, vec![80.0, 20.0]);
+    }
+}
@@ -0,0 +1,494 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+use gouache::Vec2;
+
+use crate::input::MouseButton;
+use crate::*;
+
+/// How far back in time pointer samples are used to estimate the velocity of a pan.
+const VELOCITY_WINDOW: Duration = Duration::from_millis(100);
+
+/// An event reported by a `GestureDetector`. Positions are relative to the detector.
+#[derive(Copy, Clone, Debug)]
+pub enum GestureEvent {
+    /// The left mouse button was pressed over the detector.
+    Press { x: f32, y: f32 },
+    /// The press was held in place for the long-press delay.
+    LongPress { x: f32, y: f32 },
+    /// The button was released, ending a long press.
+    LongPressEnd { x: f32, y: f32 },
+    /// The pointer moved farther than the drag threshold while pressed, starting a pan.
+    PanStart { x: f32, y: f32 },
+    /// The pointer moved during a pan. `dx` and `dy` are the movement since the last event.
+    PanUpdate { x: f32, y: f32, dx: f32, dy: f32 },
+    /// The button was released, ending a pan. The velocity is in pixels per second.
+    PanEnd { velocity_x: f32, velocity_y: f32 },
+    /// The button was released without a pan or long press having started.
+    Release { x: f32, y: f32 },
+    /// The press was cancelled, either because a recognizer in another detector won it or
+    /// because the button was released outside of the detector. No other events follow.
+    Cancel,
+}
+
+/// Recognizes pans and long presses on its child and reports them, along with presses and
+/// releases, through `on_gesture`.
+///
+/// When nested detectors see the same press, the first to recognize a pan or long press wins
+/// and the others receive `GestureEvent::Cancel`. Within one detector, a pan and a long press
+/// likewise exclude each other.
+pub struct GestureDetector<C: Elem> {
+    child: C,
+    settings: GestureSettings,
+    on_gesture: Option<Sender<GestureEvent>>,
+}
+
+#[derive(Copy, Clone)]
+struct GestureSettings {
+    pan: bool,
+    long_press: bool,
+    drag_threshold: f32,
+    long_press_delay: Duration,
+}
+
+impl<C: Elem> GestureDetector<C> {
+    pub fn new(child: C) -> GestureDetector<C> {
+        GestureDetector {
+            child,
+            settings: GestureSettings {
+                pan: true,
+                long_press: true,
+                drag_threshold: 4.0,
+                long_press_delay: Duration::from_millis(500),
+            },
+            on_gesture: None,
+        }
+    }
+
+    pub fn on_gesture(self, on_gesture: Sender<GestureEvent>) -> GestureDetector<C> {
+        GestureDetector { on_gesture: Some(on_gesture), ..self }
+    }
+
+    pub fn pan(mut self, pan: bool) -> GestureDetector<C> {
+        self.settings.pan = pan;
+        self
+    }
+
+    pub fn long_press(mut self, long_press: bool) -> GestureDetector<C> {
+        self.settings.long_press = long_press;
+        self
+    }
+
+    /// Sets how far the pointer must move while pressed before the press becomes a pan.
+    pub fn drag_threshold(mut self, drag_threshold: f32) -> GestureDetector<C> {
+        self.settings.drag_threshold = drag_threshold;
+        self
+    }
+
+    pub fn long_press_delay(mut self, long_press_delay: Duration) -> GestureDetector<C> {
+        self.settings.long_press_delay = long_press_delay;
+        self
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum GesturePhase {
+    Pending,
+    Panning,
+    LongPressed,
+}
+
+struct Tracking {
+    phase: GesturePhase,
+    start: Vec2,
+    start_time: Instant,
+    last: Vec2,
+    samples: Vec<(Instant, Vec2)>,
+}
+
+impl Tracking {
+    /// Records the pointer position for estimating velocity, dropping samples that have fallen
+    /// out of the velocity window.
+    fn sample(&mut self, mouse: Vec2) {
+        let now = Instant::now();
+        self.samples.push((now, mouse));
+        self.samples.retain(|&(time, _)| now.duration_since(time) <= VELOCITY_WINDOW);
+    }
+}
+
+struct Recognizer {
+    settings: GestureSettings,
+    origin: Vec2,
+    on_gesture: Option<Sender<GestureEvent>>,
+    tracking: Option<Tracking>,
+    /// The arena of the current press and this detector's id in it.
+    arena: Option<(GestureArena, usize)>,
+}
+
+impl Recognizer {
+    fn send(&self, event: GestureEvent) {
+        if let Some(ref on_gesture) = self.on_gesture {
+            on_gesture.send(event);
+        }
+    }
+
+    fn press(&mut self, mouse: Vec2) {
+        let now = Instant::now();
+        self.tracking = Some(Tracking {
+            phase: GesturePhase::Pending,
+            start: mouse,
+            start_time: now,
+            last: mouse,
+            samples: vec![(now, mouse)],
+        });
+        let position = mouse - self.origin;
+        self.send(GestureEvent::Press { x: position.x, y: position.y });
+    }
+
+    fn cancel(&mut self) {
+        self.arena = None;
+        if self.tracking.take().is_some() {
+            self.send(GestureEvent::Cancel);
+        }
+    }
+
+    fn velocity(tracking: &Tracking) -> Vec2 {
+        let (first_time, first) = tracking.samples[0];
+        let (last_time, last) = tracking.samples[tracking.samples.len() - 1];
+        let elapsed = last_time.duration_since(first_time);
+        let seconds = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+        if seconds > 0.0 {
+            Vec2::new((last.x - first.x) / seconds, (last.y - first.y) / seconds)
+        } else {
+            Vec2::new(0.0, 0.0)
+        }
+    }
+}
+
+/// Decides which of the detectors taking part in a press gets it. The dispatcher opens an arena
+/// for each mouse down and hands it to the handlers through the event; the first detector to
+/// recognize a gesture claims the press, cancelling the rest.
+#[derive(Clone)]
+pub(crate) struct GestureArena {
+    inner: Rc<RefCell<ArenaState>>,
+}
+
+struct ArenaState {
+    /// The cancel callback of each member that hasn't been cancelled yet, indexed by id.
+    members: Vec<Option<Box<dyn FnOnce()>>>,
+    claimed: bool,
+    closed: bool,
+}
+
+impl GestureArena {
+    pub(crate) fn new() -> GestureArena {
+        GestureArena {
+            inner: Rc::new(RefCell::new(ArenaState { members: Vec::new(), claimed: false, closed: false })),
+        }
+    }
+
+    /// Adds a member to the arena, returning its id. `cancel` is called when another member
+    /// claims the arena or the arena is closed.
+    pub(crate) fn join<F: FnOnce() + 'static>(&self, cancel: F) -> usize {
+        let mut inner = self.inner.borrow_mut();
+        inner.members.push(Some(Box::new(cancel)));
+        inner.members.len() - 1
+    }
+
+    /// Claims the arena for the member `id`, cancelling the others. Returns `false` if the
+    /// arena has already been claimed or closed.
+    pub(crate) fn claim(&self, id: usize) -> bool {
+        let losers: Vec<Box<dyn FnOnce()>> = {
+            let mut inner = self.inner.borrow_mut();
+            if inner.claimed || inner.closed {
+                return false;
+            }
+            inner.claimed = true;
+            inner.members.iter_mut().enumerate()
+                .filter(|&(i, _)| i != id)
+                .filter_map(|(_, member)| member.take())
+                .collect()
+        };
+        for cancel in losers {
+            cancel();
+        }
+        true
+    }
+
+    /// Whether a member has claimed the arena, i.e. the press was recognized as a gesture.
+    pub(crate) fn claimed(&self) -> bool {
+        self.inner.borrow().claimed
+    }
+
+    /// Cancels every remaining member, including the winner. Called by `Dispatcher::input`
+    /// after the button is released, so that detectors which didn't receive the mouse up don't
+    /// keep waiting for it.
+    pub(crate) fn close(&self) {
+        let members: Vec<Box<dyn FnOnce()>> = {
+            let mut inner = self.inner.borrow_mut();
+            inner.closed = true;
+            inner.members.iter_mut().filter_map(|member| member.take()).collect()
+        };
+        for cancel in members {
+            cancel();
+        }
+    }
+}
+
+/// Claims the current press for `recognizer`, which must not be borrowed. Returns `false` if
+/// another detector has already claimed it. A press without an arena can always be claimed.
+fn claim(recognizer: &Rc<RefCell<Recognizer>>) -> bool {
+    let arena = recognizer.borrow().arena.clone();
+    match arena {
+        Some((arena, id)) => arena.claim(id),
+        None => true,
+    }
+}
+
+impl<C: Elem> Elem for GestureDetector<C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
+
+        let (x, y) = node.position();
+        let recognizer = node.state(|| Rc::new(RefCell::new(Recognizer {
+            settings: self.settings,
+            origin: Vec2::new(0.0, 0.0),
+            on_gesture: None,
+            tracking: None,
+            arena: None,
+        }))).clone();
+
+        {
+            let mut recognizer = recognizer.borrow_mut();
+            recognizer.settings = self.settings;
+            recognizer.origin = Vec2::new(x, y);
+            recognizer.on_gesture = self.on_gesture;
+        }
+
+        // Long presses are detected here rather than in an input handler, since they happen
+        // without any input.
+        let long_press_due = recognizer.borrow().tracking.as_ref().map_or(false, |tracking| {
+            self.settings.long_press && tracking.phase == GesturePhase::Pending &&
+                tracking.start_time.elapsed() >= self.settings.long_press_delay
+        });
+        if long_press_due && claim(&recognizer) {
+            let mut recognizer = recognizer.borrow_mut();
+            let origin = recognizer.origin;
+            if let Some(ref mut tracking) = recognizer.tracking {
+                tracking.phase = GesturePhase::LongPressed;
+            }
+            let start = recognizer.tracking.as_ref().map_or(origin, |tracking| tracking.start);
+            let position = start - origin;
+            recognizer.send(GestureEvent::LongPress { x: position.x, y: position.y });
+        }
+
+        node.on_mouse_down({
+            let recognizer = recognizer.clone();
+            move |button, input_state, event| {
+                if button == MouseButton::Left {
+                    let arena = event.arena().map(|arena| {
+                        let weak = Rc::downgrade(&recognizer);
+                        let id = arena.join(move || {
+                            if let Some(recognizer) = weak.upgrade() {
+                                recognizer.borrow_mut().cancel();
+                            }
+                        });
+                        (arena.clone(), id)
+                    });
+                    let mut recognizer = recognizer.borrow_mut();
+                    recognizer.press(Vec2::new(input_state.mouse_x, input_state.mouse_y));
+                    recognizer.arena = arena;
+                }
+            }
+        });
+        node.on_mouse_move({
+            let recognizer = recognizer.clone();
+            move |input_state, event| {
+                let mouse = Vec2::new(input_state.mouse_x, input_state.mouse_y);
+
+                let start_pan = {
+                    let recognizer = recognizer.borrow();
+                    match recognizer.tracking {
+                        Some(ref tracking) if recognizer.settings.pan && tracking.phase == GesturePhase::Pending => {
+                            let delta = mouse - tracking.start;
+                            (delta.x * delta.x + delta.y * delta.y).sqrt() > recognizer.settings.drag_threshold
+                        }
+                        _ => false,
+                    }
+                };
+                if start_pan && claim(&recognizer) {
+                    let mut recognizer = recognizer.borrow_mut();
+                    let origin = recognizer.origin;
+                    if let Some(ref mut tracking) = recognizer.tracking {
+                        tracking.phase = GesturePhase::Panning;
+                    }
+                    let position = mouse - origin;
+                    recognizer.send(GestureEvent::PanStart { x: position.x, y: position.y });
+                    event.capture_pointer();
+                }
+
+                let mut recognizer = recognizer.borrow_mut();
+                let origin = recognizer.origin;
+                let update = match recognizer.tracking {
+                    Some(ref mut tracking) if tracking.phase == GesturePhase::Panning => {
+                        tracking.sample(mouse);
+                        let delta = mouse - tracking.last;
+                        tracking.last = mouse;
+                        Some(delta)
+                    }
+                    _ => None,
+                };
+                if let Some(delta) = update {
+                    let position = mouse - origin;
+                    recognizer.send(GestureEvent::PanUpdate { x: position.x, y: position.y, dx: delta.x, dy: delta.y });
+                    event.stop_propagation();
+                }
+            }
+        });
+        node.on_mouse_up({
+            let recognizer = recognizer.clone();
+            move |button, input_state, event| {
+                if button != MouseButton::Left {
+                    return;
+                }
+
+                let mut recognizer = recognizer.borrow_mut();
+                let origin = recognizer.origin;
+                recognizer.arena = None;
+                match recognizer.tracking.take() {
+                    Some(ref mut tracking) if tracking.phase == GesturePhase::Panning => {
+                        // Sampling the release drops the motion before a pause, so that a pan
+                        // which comes to rest before the button is released ends without velocity.
+                        tracking.sample(Vec2::new(input_state.mouse_x, input_state.mouse_y));
+                        let velocity = Recognizer::velocity(tracking);
+                        recognizer.send(GestureEvent::PanEnd { velocity_x: velocity.x, velocity_y: velocity.y });
+                        event.stop_propagation();
+                    }
+                    Some(ref tracking) if tracking.phase == GesturePhase::LongPressed => {
+                        let position = Vec2::new(input_state.mouse_x, input_state.mouse_y) - origin;
+                        recognizer.send(GestureEvent::LongPressEnd { x: position.x, y: position.y });
+                    }
+                    Some(_) => {
+                        let position = Vec2::new(input_state.mouse_x, input_state.mouse_y) - origin;
+                        recognizer.send(GestureEvent::Release { x: position.x, y: position.y });
+                    }
+                    None => {}
+                }
+            }
+        });
+
+        self.child.apply(node.edit_children().add(), constraints);
+        let (width, height) = node.children()[0].size();
+        let baseline = node.children()[0].baseline();
+        node.set_size(width, height);
+        node.set_baseline(baseline);
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.child.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.child.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.child.stack_placement()
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.child.key()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn claim_cancels_other_members() {
+        let cancelled = Rc::new(RefCell::new(Vec::new()));
+        let arena = GestureArena::new();
+        let join = |id: usize| {
+            let cancelled = cancelled.clone();
+            arena.join(move || cancelled.borrow_mut().push(id))
+        };
+        let first = join(0);
+        let second = join(1);
+
+        assert!(arena.claim(second));
+        assert_eq!(*cancelled.borrow(), vec![0]);
+        assert!(!arena.claim(first));
+        assert!(arena.claimed());
+
+        arena.close();
+        assert_eq!(*cancelled.borrow(), vec![0, 1]);
+    }
+
+    #[test]
+    fn closing_cancels_every_member() {
+        let cancelled = Rc::new(Cell::new(0));
+        let arena = GestureArena::new();
+        for _ in 0..2 {
+            let cancelled = cancelled.clone();
+            arena.join(move || cancelled.set(cancelled.get() + 1));
+        }
+
+        arena.close();
+        assert_eq!(cancelled.get(), 2);
+        assert!(!arena.claim(0));
+        assert!(!arena.claimed());
+    }
+
+    fn detector() -> (Node, Receiver<GestureEvent>, Rc<Cell<u32>>) {
+        let rx = Receiver::new();
+        let clicks = Rc::new(Cell::new(0));
+        let mut root = Node::new();
+        GestureDetector::new(Empty).on_gesture(rx.sender()).apply(&mut root, Constraints::tight(100.0, 100.0));
+        root.on_click({
+            let clicks = clicks.clone();
+            move |_, _, _, _| clicks.set(clicks.get() + 1)
+        });
+        (root, rx, clicks)
+    }
+
+    fn input(dispatcher: &mut Dispatcher, root: &mut Node, input: Input, x: f32, y: f32) {
+        let input_state = InputState { mouse_x: x, mouse_y: y, ..InputState::default() };
+        dispatcher.input(root, input, &input_state);
+    }
+
+    #[test]
+    fn pan_suppresses_click() {
+        let (mut root, rx, clicks) = detector();
+        let mut dispatcher = Dispatcher::new();
+        input(&mut dispatcher, &mut root, Input::MouseDown(MouseButton::Left), 10.0, 10.0);
+        input(&mut dispatcher, &mut root, Input::MouseUp(MouseButton::Left), 10.0, 10.0);
+        assert_eq!(clicks.get(), 1);
+
+        input(&mut dispatcher, &mut root, Input::MouseDown(MouseButton::Left), 10.0, 10.0);
+        input(&mut dispatcher, &mut root, Input::MouseMove, 50.0, 50.0);
+        input(&mut dispatcher, &mut root, Input::MouseUp(MouseButton::Left), 50.0, 50.0);
+        assert_eq!(clicks.get(), 1);
+        assert!(rx.poll().any(|event| match event { GestureEvent::PanEnd { .. } => true, _ => false }));
+    }
+
+    #[test]
+    fn pan_released_at_rest_has_no_velocity() {
+        let (mut root, rx, _) = detector();
+        let mut dispatcher = Dispatcher::new();
+        input(&mut dispatcher, &mut root, Input::MouseDown(MouseButton::Left), 10.0, 10.0);
+        input(&mut dispatcher, &mut root, Input::MouseMove, 50.0, 50.0);
+        thread::sleep(VELOCITY_WINDOW + Duration::from_millis(50));
+        input(&mut dispatcher, &mut root, Input::MouseUp(MouseButton::Left), 50.0, 50.0);
+
+        let velocity = rx.poll().filter_map(|event| match event {
+            GestureEvent::PanEnd { velocity_x, velocity_y } => Some((velocity_x, velocity_y)),
+            _ => None,
+        }).last();
+        assert_eq!(velocity, Some((0.0, 0.0)));
+    }
+}
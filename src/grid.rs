@@ -0,0 +1,429 @@
+use crate::*;
+use crate::flex::align_offset;
+
+/// The size of a row or column of a `Grid`.
+#[derive(Copy, Clone, PartialEq)]
+pub enum Track {
+    /// A fixed size.
+    Fixed(f32),
+    /// The natural size of the largest child that occupies only this track. Children spanning
+    /// several tracks share any size they need beyond that among the auto tracks they cover.
+    Auto,
+    /// A share of the space left over after fixed and auto tracks, in proportion to the given
+    /// factor. In an unbounded direction, fractional tracks behave like `Auto`.
+    Fraction(f32),
+}
+
+/// Where a child of a `Grid` is placed and how it is aligned within its cell.
+#[derive(Copy, Clone)]
+pub struct GridPlacement {
+    pub row: Option<usize>,
+    pub column: Option<usize>,
+    pub row_span: usize,
+    pub column_span: usize,
+    pub align_x: Align,
+    pub align_y: Align,
+}
+
+impl Default for GridPlacement {
+    fn default() -> GridPlacement {
+        GridPlacement {
+            row: None,
+            column: None,
+            row_span: 1,
+            column_span: 1,
+            align_x: Align::Start,
+            align_y: Align::Start,
+        }
+    }
+}
+
+pub struct GridCell<C: Elem> {
+    placement: GridPlacement,
+    child: C,
+}
+
+impl<C: Elem> GridCell<C> {
+    pub fn new(child: C) -> GridCell<C> {
+        GridCell { placement: GridPlacement::default(), child }
+    }
+
+    pub fn at(mut self, row: usize, column: usize) -> GridCell<C> {
+        self.placement.row = Some(row);
+        self.placement.column = Some(column);
+        self
+    }
+
+    pub fn span(mut self, rows: usize, columns: usize) -> GridCell<C> {
+        self.placement.row_span = rows.max(1);
+        self.placement.column_span = columns.max(1);
+        self
+    }
+
+    pub fn align(mut self, align_x: Align, align_y: Align) -> GridCell<C> {
+        self.placement.align_x = align_x;
+        self.placement.align_y = align_y;
+        self
+    }
+}
+
+impl<C: Elem> Elem for GridCell<C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        self.child.apply(node, constraints);
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.child.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.placement
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.child.stack_placement()
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.child.key()
+    }
+}
+
+/// Places its children into rows and columns. Children without an explicit position fill the
+/// first free cells in row-major order; rows beyond those given to `rows` are `Track::Auto`.
+///
+/// Each child is applied exactly once, so children are laid out before the size of any auto
+/// track they occupy is known. Consequently, only children whose tracks are all fixed or
+/// fractional can be stretched to fill their cell; others are aligned within it.
+pub struct Grid<C: ElemList> {
+    columns: Vec<Track>,
+    rows: Vec<Track>,
+    row_gap: f32,
+    column_gap: f32,
+    children: C,
+}
+
+impl<C: ElemList> Grid<C> {
+    pub fn new(columns: Vec<Track>, children: C) -> Grid<C> {
+        Grid { columns, rows: Vec::new(), row_gap: 0.0, column_gap: 0.0, children }
+    }
+
+    pub fn rows(self, rows: Vec<Track>) -> Grid<C> {
+        Grid { rows, ..self }
+    }
+
+    pub fn gap(self, row_gap: f32, column_gap: f32) -> Grid<C> {
+        Grid { row_gap, column_gap, ..self }
+    }
+}
+
+struct Cell {
+    row: usize,
+    column: usize,
+    row_span: usize,
+    column_span: usize,
+    align_x: Align,
+    align_y: Align,
+}
+
+fn place(placements: &[GridPlacement], columns: usize) -> Vec<Cell> {
+    let mut occupied: Vec<Vec<bool>> = Vec::new();
+    let mut cells = Vec::with_capacity(placements.len());
+    let mut cursor = (0, 0);
+
+    for placement in placements {
+        let column_span = placement.column_span.max(1).min(columns);
+        let row_span = placement.row_span.max(1);
+
+        let fits = |occupied: &Vec<Vec<bool>>, row: usize, column: usize| {
+            column + column_span <= columns &&
+            (row..row + row_span).all(|r| {
+                (column..column + column_span).all(|c| !occupied.get(r).map_or(false, |cells| cells[c]))
+            })
+        };
+
+        let (row, column) = match (placement.row, placement.column) {
+            (Some(row), Some(column)) => (row, column.min(columns - column_span)),
+            (Some(row), None) => {
+                let column = (0..columns).find(|&c| fits(&occupied, row, c)).unwrap_or(0);
+                (row, column)
+            }
+            (None, Some(column)) => {
+                let column = column.min(columns - column_span);
+                let row = (0..).find(|&r| fits(&occupied, r, column)).unwrap();
+                (row, column)
+            }
+            (None, None) => {
+                let (mut row, mut column) = cursor;
+                while !fits(&occupied, row, column) {
+                    column += 1;
+                    if column + column_span > columns {
+                        column = 0;
+                        row += 1;
+                    }
+                }
+                cursor = (row, column + column_span);
+                (row, column)
+            }
+        };
+
+        while occupied.len() < row + row_span {
+            occupied.push(vec![false; columns]);
+        }
+        for r in row..row + row_span {
+            for c in column..column + column_span {
+                occupied[r][c] = true;
+            }
+        }
+
+        cells.push(Cell { row, column, row_span, column_span, align_x: placement.align_x, align_y: placement.align_y });
+    }
+
+    cells
+}
+
+fn span_size(sizes: &[f32], start: usize, span: usize, gap: f32) -> f32 {
+    sizes[start..start + span].iter().sum::<f32>() + gap * (span - 1) as f32
+}
+
+/// Resolves the sizes of auto and fractional tracks. `natural` holds the start track, span and
+/// natural size of each child.
+fn resolve_tracks(tracks: &[Track], natural: &[(usize, usize, f32)], available: f32, gap: f32) -> Vec<f32> {
+    let bounded = available.is_finite();
+    let is_auto = |track: &Track| match *track {
+        Track::Auto => true,
+        Track::Fraction(_) => !bounded,
+        Track::Fixed(_) => false,
+    };
+
+    let mut sizes: Vec<f32> = tracks.iter().map(|track| match *track {
+        Track::Fixed(size) => size,
+        _ => 0.0,
+    }).collect();
+    for &(start, span, size) in natural {
+        if span == 1 && is_auto(&tracks[start]) {
+            sizes[start] = sizes[start].max(size);
+        }
+    }
+
+    // Children spanning several tracks are handled from the narrowest span up, each growing the
+    // auto tracks it covers equally by whatever it still lacks. Spans covering a fractional
+    // track with space to share are left to that track.
+    let mut spanning: Vec<&(usize, usize, f32)> = natural.iter().filter(|&&(_, span, _)| span > 1).collect();
+    spanning.sort_by_key(|&&(_, span, _)| span);
+    for &&(start, span, size) in spanning.iter() {
+        let span_tracks = &tracks[start..start + span];
+        if bounded && span_tracks.iter().any(|track| match *track { Track::Fraction(_) => true, _ => false }) {
+            continue;
+        }
+
+        let auto_count = span_tracks.iter().filter(|track| is_auto(track)).count();
+        let excess = size - span_size(&sizes, start, span, gap);
+        if auto_count > 0 && excess > 0.0 {
+            for (track_size, track) in sizes[start..start + span].iter_mut().zip(span_tracks) {
+                if is_auto(track) {
+                    *track_size += excess / auto_count as f32;
+                }
+            }
+        }
+    }
+
+    if bounded {
+        let total_fraction: f32 = tracks.iter().map(|track| match *track {
+            Track::Fraction(fraction) => fraction,
+            _ => 0.0,
+        }).sum();
+        let used = sizes.iter().sum::<f32>() + gap * tracks.len().saturating_sub(1) as f32;
+        let remaining = (available - used).max(0.0);
+        if total_fraction > 0.0 {
+            for (size, track) in sizes.iter_mut().zip(tracks) {
+                if let Track::Fraction(fraction) = *track {
+                    *size = remaining * fraction / total_fraction;
+                }
+            }
+        }
+    }
+
+    sizes
+}
+
+/// Returns the size of a span of tracks if it is already known, i.e. if every track in it is
+/// fixed, or fractional with a bounded amount of space to share.
+fn known_span(tracks: &[Track], sizes: Option<&[f32]>, start: usize, span: usize, gap: f32) -> Option<f32> {
+    let mut total = gap * (span - 1) as f32;
+    for i in start..start + span {
+        total += match (tracks[i], sizes) {
+            (Track::Fixed(size), _) => size,
+            (Track::Fraction(_), Some(sizes)) => sizes[i],
+            _ => return None,
+        };
+    }
+    Some(total)
+}
+
+fn cell_constraints(width: Option<f32>, height: Option<f32>, align_x: Align, align_y: Align) -> Constraints {
+    let (min_width, max_width) = match width {
+        Some(width) if align_x == Align::Stretch => (width, width),
+        Some(width) => (0.0, width),
+        None => (0.0, std::f32::INFINITY),
+    };
+    let (min_height, max_height) = match height {
+        Some(height) if align_y == Align::Stretch => (height, height),
+        Some(height) => (0.0, height),
+        None => (0.0, std::f32::INFINITY),
+    };
+    Constraints::new(min_width, max_width, min_height, max_height)
+}
+
+impl<C: ElemList> Elem for Grid<C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
+
+        let Grid { columns, rows, row_gap, column_gap, children } = self;
+        let columns = if columns.is_empty() { vec![Track::Auto] } else { columns };
+        children.with_items(&mut |items| {
+            grid_layout(node, items, &columns, &rows, row_gap, column_gap, constraints);
+        });
+    }
+}
+
+fn grid_layout(node: &mut Node, items: &mut [&mut dyn ElemItem], columns: &[Track], row_tracks: &[Track], row_gap: f32, column_gap: f32, constraints: Constraints) {
+    let placements: Vec<GridPlacement> = items.iter().map(|item| item.grid_placement()).collect();
+    let cells = place(&placements, columns.len());
+
+    let row_count = cells.iter().map(|cell| cell.row + cell.row_span).max().unwrap_or(0).max(row_tracks.len());
+    let rows: Vec<Track> = (0..row_count).map(|i| row_tracks.get(i).cloned().unwrap_or(Track::Auto)).collect();
+
+    {
+        let mut children = node.edit_children();
+        for item in items.iter() {
+            children.add_with_key(item.key());
+        }
+    }
+
+    let touches_fraction = |cell: &Cell| {
+        columns[cell.column..cell.column + cell.column_span].iter().any(|track| match *track {
+            Track::Fraction(_) => true,
+            _ => false,
+        })
+    };
+
+    // Children in fixed and auto columns are applied first, so that the auto columns can be
+    // measured before the remaining width is shared among the fractional ones.
+    let mut pending = Vec::new();
+    for (i, (item, cell)) in items.iter_mut().zip(cells.iter()).enumerate() {
+        if touches_fraction(cell) && constraints.has_bounded_width() {
+            pending.push(i);
+            continue;
+        }
+
+        let width = known_span(columns, None, cell.column, cell.column_span, column_gap);
+        let height = known_span(&rows, None, cell.row, cell.row_span, row_gap);
+        item.apply(&mut node.children_mut()[i], cell_constraints(width, height, cell.align_x, cell.align_y));
+    }
+
+    let mut natural_widths = Vec::with_capacity(cells.len());
+    for (i, cell) in cells.iter().enumerate() {
+        if !pending.contains(&i) {
+            natural_widths.push((cell.column, cell.column_span, node.children()[i].size().0));
+        }
+    }
+    let column_widths = resolve_tracks(columns, &natural_widths, constraints.max_width(), column_gap);
+
+    for i in pending {
+        let cell = &cells[i];
+        let width = known_span(columns, Some(&column_widths), cell.column, cell.column_span, column_gap);
+        let height = known_span(&rows, None, cell.row, cell.row_span, row_gap);
+        items[i].apply(&mut node.children_mut()[i], cell_constraints(width, height, cell.align_x, cell.align_y));
+    }
+
+    let natural_heights: Vec<(usize, usize, f32)> = cells.iter().enumerate().map(|(i, cell)| {
+        (cell.row, cell.row_span, node.children()[i].size().1)
+    }).collect();
+    let row_heights = resolve_tracks(&rows, &natural_heights, constraints.max_height(), row_gap);
+
+    let mut column_positions = Vec::with_capacity(columns.len());
+    let mut x = 0.0;
+    for width in column_widths.iter() {
+        column_positions.push(x);
+        x += width + column_gap;
+    }
+    let mut row_positions = Vec::with_capacity(rows.len());
+    let mut y = 0.0;
+    for height in row_heights.iter() {
+        row_positions.push(y);
+        y += height + row_gap;
+    }
+
+    for (child, cell) in node.children_mut().iter_mut().zip(cells.iter()) {
+        let cell_width = span_size(&column_widths, cell.column, cell.column_span, column_gap);
+        let cell_height = span_size(&row_heights, cell.row, cell.row_span, row_gap);
+        let (width, height) = child.size();
+        child.set_offset(
+            column_positions[cell.column] + align_offset(cell.align_x, cell_width, width),
+            row_positions[cell.row] + align_offset(cell.align_y, cell_height, height),
+        );
+    }
+
+    let width = (x - column_gap).max(0.0);
+    let height = (y - row_gap).max(0.0);
+    let (width, height) = constraints.constrain(width, height);
+    node.set_size(width, height);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(cells: &[Cell]) -> Vec<(usize, usize)> {
+        cells.iter().map(|cell| (cell.row, cell.column)).collect()
+    }
+
+    #[test]
+    fn auto_placement_fills_rows() {
+        let placements = vec![GridPlacement::default(); 4];
+        assert_eq!(positions(&place(&placements, 3)), vec![(0, 0), (0, 1), (0, 2), (1, 0)]);
+    }
+
+    #[test]
+    fn auto_placement_skips_occupied_cells() {
+        let wide = GridPlacement { column_span: 2, ..GridPlacement::default() };
+        let fixed = GridPlacement { row: Some(1), column: Some(0), row_span: 2, ..GridPlacement::default() };
+        let placements = vec![wide, fixed, GridPlacement::default(), wide];
+        let cells = place(&placements, 3);
+        assert_eq!(positions(&cells), vec![(0, 0), (1, 0), (0, 2), (1, 1)]);
+        assert_eq!(cells[0].column_span, 2);
+    }
+
+    #[test]
+    fn spans_are_clamped_to_columns() {
+        let placements = vec![GridPlacement { column: Some(2), column_span: 5, ..GridPlacement::default() }];
+        let cells = place(&placements, 3);
+        assert_eq!((cells[0].column, cells[0].column_span), (0, 3));
+    }
+
+    #[test]
+    fn fractions_share_remaining_space() {
+        let tracks = [Track::Fixed(10.0), Track::Auto, Track::Fraction(1.0), Track::Fraction(3.0)];
+        let sizes = resolve_tracks(&tracks, &[(1, 1, 30.0), (2, 1, 500.0)], 130.0, 5.0);
+        assert_eq!(sizes, vec![10.0, 30.0, 18.75, 56.25]);
+    }
+
+    #[test]
+    fn unbounded_fractions_act_like_auto() {
+        let tracks = [Track::Fraction(1.0), Track::Auto];
+        let sizes = resolve_tracks(&tracks, &[(0, 1, 20.0), (1, 1, 15.0)], std::f32::INFINITY, 0.0);
+        assert_eq!(sizes, vec![20.0, 15.0]);
+    }
+
+    #[test]
+    fn spanning_children_grow_auto_tracks() {
+        let tracks = [Track::Auto, Track::Auto, Track::Fixed(10.0)];
+        let sizes = resolve_tracks(&tracks, &[(0, 1, 10.0), (0, 3, 70.0)], std::f32::INFINITY, 10.0);
+        assert_eq!(sizes, vec![25.0, 15.0, 10.0]);
+
+        // A child that already fits leaves the tracks alone.
+        let sizes = resolve_tracks(&tracks, &[(0, 1, 40.0), (0, 2, 30.0)], std::f32::INFINITY, 0.0);
+        assert_eq!(sizes, vec![40.0, 0.0, 10.0]);
+    }
+}
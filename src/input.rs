@@ -1,8 +1,15 @@
 #![allow(dead_code)]
 
+use std::cell::Cell;
+use std::time::Duration;
+
+use crate::gesture::GestureArena;
+
 #[derive(Copy, Clone)]
 pub enum Input {
     MouseMove,
+    /// The mouse left the window. Nothing is hovered until it moves back in.
+    MouseLeave,
     MouseDown(MouseButton),
     MouseUp(MouseButton),
     Scroll(f32, f32),
@@ -47,7 +54,7 @@ impl Default for Modifiers {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, PartialEq, Eq)]
 pub enum Key {
     Key0,
     Key1,
@@ -174,3 +181,115 @@ pub enum MouseButton {
     Middle,
     Right,
 }
+
+/// The stage of dispatch at which a handler is called.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Phase {
+    /// The event is travelling from the root down to the target, and is being delivered to
+    /// capture handlers of the target's ancestors.
+    Capture,
+    /// The event is being delivered to the target itself, first to its capture handler and
+    /// then to its regular handler.
+    Target,
+    /// The event is travelling from the target back up to the root, and is being delivered to
+    /// regular handlers of the target's ancestors.
+    Bubble,
+}
+
+/// Passed to every input handler along with the input itself, allowing the handler to stop
+/// the event from reaching any further nodes or to suppress the default behavior associated
+/// with it.
+pub struct Event {
+    phase: Cell<Phase>,
+    propagation_stopped: Cell<bool>,
+    default_prevented: Cell<bool>,
+    capture: Cell<Option<bool>>,
+    arena: Option<GestureArena>,
+}
+
+impl Event {
+    pub fn new() -> Event {
+        Event {
+            phase: Cell::new(Phase::Target),
+            propagation_stopped: Cell::new(false),
+            default_prevented: Cell::new(false),
+            capture: Cell::new(None),
+            arena: None,
+        }
+    }
+
+    pub(crate) fn with_arena(self, arena: GestureArena) -> Event {
+        Event { arena: Some(arena), ..self }
+    }
+
+    /// The gesture arena for the press, if this is a mouse down event.
+    pub(crate) fn arena(&self) -> Option<&GestureArena> {
+        self.arena.as_ref()
+    }
+
+    pub fn phase(&self) -> Phase {
+        self.phase.get()
+    }
+
+    pub(crate) fn set_phase(&self, phase: Phase) {
+        self.phase.set(phase);
+    }
+
+    /// Prevents the event from being delivered to any node after the current one.
+    pub fn stop_propagation(&self) {
+        self.propagation_stopped.set(true);
+    }
+
+    pub fn propagation_stopped(&self) -> bool {
+        self.propagation_stopped.get()
+    }
+
+    /// Suppresses the default behavior of the event: moving focus to the clicked node for
+    /// mouse down, and moving focus to the next node for Tab.
+    pub fn prevent_default(&self) {
+        self.default_prevented.set(true);
+    }
+
+    pub fn default_prevented(&self) -> bool {
+        self.default_prevented.get()
+    }
+
+    /// Captures the pointer for the node whose handler is running, typically in response to
+    /// mouse down. Until it releases the pointer or the button being pressed (or, outside of
+    /// mouse down, the most recently pressed button) is released, that node is the target of
+    /// every mouse move and mouse up event, even when the mouse is outside of it or outside the
+    /// window.
+    pub fn capture_pointer(&self) {
+        self.capture.set(Some(true));
+    }
+
+    /// Releases the pointer if it is captured by the node whose handler is running.
+    pub fn release_pointer(&self) {
+        self.capture.set(Some(false));
+    }
+
+    /// Returns and clears the capture request made by the last handler, if any: `true` to
+    /// capture the pointer and `false` to release it.
+    pub fn take_capture_request(&self) -> Option<bool> {
+        self.capture.take()
+    }
+}
+
+/// Thresholds used to decide whether consecutive presses form a double or triple click.
+#[derive(Copy, Clone)]
+pub struct ClickSettings {
+    /// The longest time between two presses that still counts as a repeated click.
+    pub interval: Duration,
+    /// The farthest the mouse may move between two presses that still count as a repeated
+    /// click.
+    pub distance: f32,
+}
+
+impl Default for ClickSettings {
+    fn default() -> ClickSettings {
+        ClickSettings {
+            interval: Duration::from_millis(500),
+            distance: 4.0,
+        }
+    }
+}
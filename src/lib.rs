@@ -3,56 +3,325 @@ pub use gouache;
 pub mod input;
 pub mod backends;
 
+mod context;
+mod edit;
+mod flex;
+mod gesture;
+mod grid;
+mod rich_text;
+mod stack;
+mod text_area;
+mod text_input;
+mod theme;
+
+pub use context::{Provide, context};
+pub use flex::{Align, FlexParams, Flexible, Justify};
+pub use gesture::{GestureDetector, GestureEvent};
+pub use grid::{Grid, GridCell, GridPlacement, Track};
+pub use rich_text::{RichText, Span};
+pub use stack::{Layer, Stack, StackPlacement};
+pub use text_area::TextArea;
+pub use text_input::TextInput;
+pub use theme::{ButtonStyle, ScrollbarStyle, TextInputStyle, Theme, Themed};
+
 use std::any::{Any, TypeId};
 use std::cell::Cell;
 use std::collections::HashMap;
-use std::marker::PhantomData;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::rc::Rc;
+use std::time::Instant;
 
-use gouache::{Color, Frame, Font, Glyph, Path, Vec2, Mat2x2};
-use input::{Input, InputState, MouseButton, Key};
+use gouache::{Color, Frame, Font, Glyph, Path, PathBuilder, Vec2, Mat2x2};
+use input::{ClickSettings, Event, Input, InputState, MouseButton, Key, Phase};
+use flex::flex_layout;
+use gesture::GestureArena;
 
+/// Returns an `Id` for the call site. An optional key can be given to distinguish elements
+/// created at the same site, e.g. in a loop: `id!(index)`.
 #[macro_export]
 macro_rules! id {
-    () => { { static ID: u8 = 0; &ID as *const u8 as usize } }
+    () => { $crate::Id::from_site(concat!(module_path!(), ":", file!(), ":", line!(), ":", column!())) };
+    ($key:expr) => { $crate::id!().with_key($key) };
+}
+
+/// Identifies the element that owns a node, so that the node's state can be reset when a
+/// different element is applied to it. Built from a call site with `id!`, and optionally
+/// refined with a type and user keys.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Id(u64);
+
+impl Id {
+    pub fn from_site(site: &'static str) -> Id {
+        Id(hash_key(site))
+    }
+
+    /// Mixes the type `T` into this id. Generic elements should use `id!().of::<Self>()`,
+    /// since statics (and therefore call sites) are shared between monomorphizations.
+    ///
+    /// This uses the type's name rather than its `TypeId`, so that types with non-`'static`
+    /// lifetimes can be used. Names aren't guaranteed to be unique, but a collision can at
+    /// worst keep a node's state when it should have been reset.
+    pub fn of<T: ?Sized>(self) -> Id {
+        Id(hash_key((self.0, std::any::type_name::<T>())))
+    }
+
+    pub fn with_key<K: Hash>(self, key: K) -> Id {
+        Id(hash_key((self.0, key)))
+    }
 }
 
 pub trait Elem {
-    fn apply(self, node: &mut Node, bounds: Bounds);
+    fn apply(self, node: &mut Node, constraints: Constraints);
+
+    /// How this element is sized when it is a child of a `Row` or `Col`.
+    fn flex(&self) -> FlexParams {
+        FlexParams::default()
+    }
+
+    /// Where this element is placed when it is a child of a `Grid`.
+    fn grid_placement(&self) -> GridPlacement {
+        GridPlacement::default()
+    }
+
+    /// How this element is positioned when it is a child of a `Stack`.
+    fn stack_placement(&self) -> StackPlacement {
+        StackPlacement::default()
+    }
+
+    /// The key used to match this element with a node from the previous frame. Elements
+    /// without a key are matched by position.
+    fn key(&self) -> Option<u64> {
+        None
+    }
+
+    /// Erases the type of this element, so that elements of different types can be returned
+    /// from the branches of a `match` or stored in the same `Vec`.
+    fn boxed<'a>(self) -> AnyElem<'a> where Self: Sized + 'a {
+        AnyElem::new(self)
+    }
+}
+
+/// A type-erased element.
+pub struct AnyElem<'a> {
+    elem: Box<dyn DynElem + 'a>,
+}
+
+impl<'a> AnyElem<'a> {
+    pub fn new<E: Elem + 'a>(elem: E) -> AnyElem<'a> {
+        AnyElem { elem: Box::new(elem) }
+    }
+}
+
+impl<'a> Elem for AnyElem<'a> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        self.elem.apply_boxed(node, constraints);
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.elem.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.elem.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.elem.stack_placement()
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.elem.key()
+    }
+
+    fn boxed<'b>(self) -> AnyElem<'b> where Self: 'b {
+        self
+    }
+}
+
+/// An object-safe counterpart to `Elem`, implemented for every element.
+trait DynElem {
+    fn apply_boxed(self: Box<Self>, node: &mut Node, constraints: Constraints);
+    fn flex(&self) -> FlexParams;
+    fn grid_placement(&self) -> GridPlacement;
+    fn stack_placement(&self) -> StackPlacement;
+    fn key(&self) -> Option<u64>;
+}
+
+impl<E: Elem> DynElem for E {
+    fn apply_boxed(self: Box<Self>, node: &mut Node, constraints: Constraints) {
+        (*self).apply(node, constraints);
+    }
+
+    fn flex(&self) -> FlexParams {
+        Elem::flex(self)
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        Elem::grid_placement(self)
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        Elem::stack_placement(self)
+    }
+
+    fn key(&self) -> Option<u64> {
+        Elem::key(self)
+    }
 }
 
 pub trait ElemList {
-    fn apply_all(self, cursor: &mut Cursor, bounds: Bounds);
+    fn apply_all(self, cursor: &mut Cursor, constraints: Constraints);
+
+    /// Calls `f` with the individual elements of the list, so that a container can apply them
+    /// in any order. The elements are passed in place rather than boxed.
+    fn with_items(self, f: &mut dyn FnMut(&mut [&mut dyn ElemItem]));
 
     fn chain<N: ElemList>(self, next: N) -> Chain<Self, N> where Self: Sized {
         Chain(self, next)
     }
 }
 
+/// An element of an `ElemList` waiting to be applied by its container.
+pub trait ElemItem {
+    fn flex(&self) -> FlexParams;
+    fn grid_placement(&self) -> GridPlacement;
+    fn stack_placement(&self) -> StackPlacement;
+    fn key(&self) -> Option<u64>;
+
+    /// Applies the element. An item can only be applied once; later calls do nothing.
+    fn apply(&mut self, node: &mut Node, constraints: Constraints);
+}
+
+impl<E: Elem> ElemItem for Option<E> {
+    fn flex(&self) -> FlexParams {
+        self.as_ref().map_or_else(FlexParams::default, |elem| elem.flex())
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.as_ref().map_or_else(GridPlacement::default, |elem| elem.grid_placement())
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.as_ref().map_or_else(StackPlacement::default, |elem| elem.stack_placement())
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.as_ref().and_then(|elem| elem.key())
+    }
+
+    fn apply(&mut self, node: &mut Node, constraints: Constraints) {
+        if let Some(elem) = self.take() {
+            elem.apply(node, constraints);
+        }
+    }
+}
+
 impl ElemList for () {
-    fn apply_all(self, cursor: &mut Cursor, bounds: Bounds) {}
+    fn apply_all(self, cursor: &mut Cursor, constraints: Constraints) {}
+
+    fn with_items(self, f: &mut dyn FnMut(&mut [&mut dyn ElemItem])) {
+        f(&mut []);
+    }
 }
 
 pub struct Chain<A: ElemList, B: ElemList>(A, B);
 
 impl<A: ElemList, B: ElemList> ElemList for Chain<A, B> {
-    fn apply_all(self, cursor: &mut Cursor, bounds: Bounds) {
-        self.0.apply_all(cursor, bounds);
-        self.1.apply_all(cursor, bounds);
+    fn apply_all(self, cursor: &mut Cursor, constraints: Constraints) {
+        self.0.apply_all(cursor, constraints);
+        self.1.apply_all(cursor, constraints);
+    }
+
+    fn with_items(self, f: &mut dyn FnMut(&mut [&mut dyn ElemItem])) {
+        let Chain(first, second) = self;
+        let mut second = Some(second);
+        first.with_items(&mut |first| {
+            if let Some(second) = second.take() {
+                second.with_items(&mut |second| {
+                    let mut items: Vec<&mut dyn ElemItem> = Vec::with_capacity(first.len() + second.len());
+                    items.extend(first.iter_mut().map(|item| &mut **item as &mut dyn ElemItem));
+                    items.extend(second.iter_mut().map(|item| &mut **item as &mut dyn ElemItem));
+                    f(&mut items);
+                });
+            }
+        });
     }
 }
 
 impl<E: Elem> ElemList for E {
-    fn apply_all(self, cursor: &mut Cursor, bounds: Bounds) {
-        self.apply(cursor.add(), bounds);
+    fn apply_all(self, cursor: &mut Cursor, constraints: Constraints) {
+        cursor.apply(self, constraints);
+    }
+
+    fn with_items(self, f: &mut dyn FnMut(&mut [&mut dyn ElemItem])) {
+        f(&mut [&mut Some(self) as &mut dyn ElemItem]);
+    }
+}
+
+impl<E: Elem> ElemList for Vec<E> {
+    fn apply_all(self, cursor: &mut Cursor, constraints: Constraints) {
+        for elem in self {
+            cursor.apply(elem, constraints);
+        }
+    }
+
+    fn with_items(self, f: &mut dyn FnMut(&mut [&mut dyn ElemItem])) {
+        let mut slots: Vec<Option<E>> = self.into_iter().map(Some).collect();
+        let mut items: Vec<&mut dyn ElemItem> = slots.iter_mut().map(|slot| slot as &mut dyn ElemItem).collect();
+        f(&mut items);
+    }
+}
+
+impl<E: Elem> ElemList for Option<E> {
+    fn apply_all(self, cursor: &mut Cursor, constraints: Constraints) {
+        if let Some(elem) = self {
+            cursor.apply(elem, constraints);
+        }
+    }
+
+    fn with_items(self, f: &mut dyn FnMut(&mut [&mut dyn ElemItem])) {
+        match self {
+            Some(elem) => f(&mut [&mut Some(elem) as &mut dyn ElemItem]),
+            None => f(&mut []),
+        }
+    }
+}
+
+/// An `ElemList` containing each element produced by an iterator.
+pub struct Each<I: Iterator> {
+    iter: I,
+}
+
+impl<I: Iterator> Each<I> {
+    pub fn new<T: IntoIterator<IntoIter = I>>(iter: T) -> Each<I> {
+        Each { iter: iter.into_iter() }
+    }
+}
+
+impl<I: Iterator> ElemList for Each<I> where I::Item: Elem {
+    fn apply_all(self, cursor: &mut Cursor, constraints: Constraints) {
+        for elem in self.iter {
+            cursor.apply(elem, constraints);
+        }
+    }
+
+    fn with_items(self, f: &mut dyn FnMut(&mut [&mut dyn ElemItem])) {
+        let mut slots: Vec<Option<I::Item>> = self.iter.map(Some).collect();
+        let mut items: Vec<&mut dyn ElemItem> = slots.iter_mut().map(|slot| slot as &mut dyn ElemItem).collect();
+        f(&mut items);
     }
 }
 
 macro_rules! tuple_elem_list {
     ( $(($i:tt) $E:ident),+ ) => {
         impl<$($E: Elem),+> ElemList for ($($E,)+) {
-            fn apply_all(self, cursor: &mut Cursor, bounds: Bounds) {
-                $(self.$i.apply(cursor.add(), bounds);)+
+            fn apply_all(self, cursor: &mut Cursor, constraints: Constraints) {
+                $(cursor.apply(self.$i, constraints);)+
+            }
+
+            fn with_items(self, f: &mut dyn FnMut(&mut [&mut dyn ElemItem])) {
+                f(&mut [$(&mut Some(self.$i) as &mut dyn ElemItem),+]);
             }
         }
     }
@@ -71,39 +340,143 @@ tuple_elem_list!((0) A, (1) B, (2) C, (3) D, (4) E, (5) F, (6) G, (7) H, (8) I,
 tuple_elem_list!((0) A, (1) B, (2) C, (3) D, (4) E, (5) F, (6) G, (7) H, (8) I, (9) J, (10) K);
 tuple_elem_list!((0) A, (1) B, (2) C, (3) D, (4) E, (5) F, (6) G, (7) H, (8) I, (9) J, (10) K, (11) L);
 
-#[derive(Copy, Clone)]
-pub struct Bounds {
-    width: f32,
-    height: f32,
+/// The range of sizes an element may choose from, passed down from its parent during `apply`.
+///
+/// An axis is tight when its minimum and maximum are equal, and unbounded when its maximum is
+/// infinite. Every element must pick a size within its constraints; `constrain` clamps a
+/// desired size into range.
+#[derive(Copy, Clone, PartialEq)]
+pub struct Constraints {
+    min_width: f32,
+    max_width: f32,
+    min_height: f32,
+    max_height: f32,
 }
 
-impl Bounds {
-    pub fn new(width: f32, height: f32) -> Bounds {
-        Bounds { width, height }
+impl Constraints {
+    pub fn new(min_width: f32, max_width: f32, min_height: f32, max_height: f32) -> Constraints {
+        let min_width = min_width.max(0.0);
+        let min_height = min_height.max(0.0);
+        Constraints {
+            min_width,
+            max_width: max_width.max(min_width),
+            min_height,
+            max_height: max_height.max(min_height),
+        }
+    }
+
+    pub fn tight(width: f32, height: f32) -> Constraints {
+        Constraints::new(width, width, height, height)
+    }
+
+    pub fn loose(width: f32, height: f32) -> Constraints {
+        Constraints::new(0.0, width, 0.0, height)
+    }
+
+    pub fn unbounded() -> Constraints {
+        Constraints::new(0.0, std::f32::INFINITY, 0.0, std::f32::INFINITY)
+    }
+
+    pub fn min_width(&self) -> f32 {
+        self.min_width
+    }
+
+    pub fn max_width(&self) -> f32 {
+        self.max_width
+    }
+
+    pub fn min_height(&self) -> f32 {
+        self.min_height
+    }
+
+    pub fn max_height(&self) -> f32 {
+        self.max_height
+    }
+
+    pub fn has_bounded_width(&self) -> bool {
+        self.max_width.is_finite()
+    }
+
+    pub fn has_bounded_height(&self) -> bool {
+        self.max_height.is_finite()
+    }
+
+    pub fn is_tight(&self) -> bool {
+        self.min_width == self.max_width && self.min_height == self.max_height
+    }
+
+    /// Removes the minimum size, allowing the child to be as small as it likes.
+    pub fn loosen(&self) -> Constraints {
+        Constraints::new(0.0, self.max_width, 0.0, self.max_height)
+    }
+
+    /// Shrinks both the minimum and maximum size, e.g. to make room for padding.
+    pub fn deflate(&self, width: f32, height: f32) -> Constraints {
+        Constraints::new(
+            self.min_width - width,
+            self.max_width - width,
+            self.min_height - height,
+            self.max_height - height,
+        )
+    }
+
+    pub fn with_width(&self, min_width: f32, max_width: f32) -> Constraints {
+        Constraints::new(min_width, max_width, self.min_height, self.max_height)
+    }
+
+    pub fn with_height(&self, min_height: f32, max_height: f32) -> Constraints {
+        Constraints::new(self.min_width, self.max_width, min_height, max_height)
+    }
+
+    pub fn constrain(&self, width: f32, height: f32) -> (f32, f32) {
+        (
+            width.min(self.max_width).max(self.min_width),
+            height.min(self.max_height).max(self.min_height),
+        )
     }
 }
 
 pub struct Node {
-    tag: usize,
+    tag: Id,
+    key: Option<u64>,
     offset: Vec2,
+    position: Vec2,
     size: Vec2,
+    baseline: Option<f32>,
     shape: Shape,
     children: Vec<Node>,
 
     hover: bool,
     dragging: bool,
+    focusable: bool,
+    focused: bool,
+    focus_requested: bool,
+    focus_scope: bool,
+    tab_index: i32,
+    scroll_request: Option<(Vec2, Vec2)>,
+    selected_text: Option<String>,
+    clip: bool,
     handlers: Handlers,
     state: Box<dyn Any>,
 }
 
 struct Handlers {
-    on_mouse_move: Option<Box<dyn Fn(&InputState)>>,
-    on_mouse_down: Option<Box<dyn Fn(MouseButton, &InputState)>>,
-    on_mouse_up: Option<Box<dyn Fn(MouseButton, &InputState)>>,
-    on_scroll: Option<Box<dyn Fn(f32, f32, &InputState)>>,
-    on_key_down: Option<Box<dyn Fn(Key, &InputState)>>,
-    on_key_up: Option<Box<dyn Fn(Key, &InputState)>>,
-    on_char: Option<Box<dyn Fn(char, &InputState)>>,
+    on_mouse_move: Option<Box<dyn Fn(&InputState, &Event)>>,
+    on_mouse_down: Option<Box<dyn Fn(MouseButton, &InputState, &Event)>>,
+    on_mouse_up: Option<Box<dyn Fn(MouseButton, &InputState, &Event)>>,
+    on_scroll: Option<Box<dyn Fn(f32, f32, &InputState, &Event)>>,
+    on_mouse_move_capture: Option<Box<dyn Fn(&InputState, &Event)>>,
+    on_mouse_down_capture: Option<Box<dyn Fn(MouseButton, &InputState, &Event)>>,
+    on_mouse_up_capture: Option<Box<dyn Fn(MouseButton, &InputState, &Event)>>,
+    on_scroll_capture: Option<Box<dyn Fn(f32, f32, &InputState, &Event)>>,
+    on_mouse_enter: Option<Box<dyn Fn(&InputState)>>,
+    on_mouse_leave: Option<Box<dyn Fn(&InputState)>>,
+    on_click: Option<Box<dyn Fn(MouseButton, u32, &InputState, &Event)>>,
+    on_key_down: Option<Box<dyn Fn(Key, &InputState, &Event)>>,
+    on_key_up: Option<Box<dyn Fn(Key, &InputState, &Event)>>,
+    on_char: Option<Box<dyn Fn(char, &InputState, &Event)>>,
+    on_cut: Option<Box<dyn Fn(&InputState)>>,
+    on_paste: Option<Box<dyn Fn(&str, &InputState)>>,
 }
 
 impl Default for Handlers {
@@ -113,9 +486,67 @@ impl Default for Handlers {
             on_mouse_down: None,
             on_mouse_up: None,
             on_scroll: None,
+            on_mouse_move_capture: None,
+            on_mouse_down_capture: None,
+            on_mouse_up_capture: None,
+            on_scroll_capture: None,
+            on_mouse_enter: None,
+            on_mouse_leave: None,
+            on_click: None,
             on_key_down: None,
             on_key_up: None,
             on_char: None,
+            on_cut: None,
+            on_paste: None,
+        }
+    }
+}
+
+impl Handlers {
+    /// Calls the handler for `input`, either the capture handler or the regular one.
+    /// Keyboard events have no capture handlers.
+    fn call(&self, input: Input, input_state: &InputState, event: &Event, capture: bool) {
+        match input {
+            Input::MouseMove => {
+                let handler = if capture { &self.on_mouse_move_capture } else { &self.on_mouse_move };
+                if let Some(handler) = handler {
+                    handler(input_state, event);
+                }
+            }
+            Input::MouseDown(button) => {
+                let handler = if capture { &self.on_mouse_down_capture } else { &self.on_mouse_down };
+                if let Some(handler) = handler {
+                    handler(button, input_state, event);
+                }
+            }
+            Input::MouseUp(button) => {
+                let handler = if capture { &self.on_mouse_up_capture } else { &self.on_mouse_up };
+                if let Some(handler) = handler {
+                    handler(button, input_state, event);
+                }
+            }
+            Input::Scroll(dx, dy) => {
+                let handler = if capture { &self.on_scroll_capture } else { &self.on_scroll };
+                if let Some(handler) = handler {
+                    handler(dx, dy, input_state, event);
+                }
+            }
+            Input::KeyDown(key) if !capture => {
+                if let Some(ref on_key_down) = self.on_key_down {
+                    on_key_down(key, input_state, event);
+                }
+            }
+            Input::KeyUp(key) if !capture => {
+                if let Some(ref on_key_up) = self.on_key_up {
+                    on_key_up(key, input_state, event);
+                }
+            }
+            Input::Char(c) if !capture => {
+                if let Some(ref on_char) = self.on_char {
+                    on_char(c, input_state, event);
+                }
+            }
+            _ => {}
         }
     }
 }
@@ -125,30 +556,47 @@ impl Node {
         Node::with_tag(id!())
     }
 
-    fn with_tag(tag: usize) -> Node {
+    fn with_tag(tag: Id) -> Node {
         Node {
             tag,
+            key: None,
             offset: Vec2::new(0.0, 0.0),
+            position: Vec2::new(0.0, 0.0),
             size: Vec2::new(0.0, 0.0),
+            baseline: None,
             shape: Shape::Empty,
             children: Vec::new(),
 
             hover: false,
             dragging: false,
+            focusable: false,
+            focused: false,
+            focus_requested: false,
+            focus_scope: false,
+            tab_index: 0,
+            scroll_request: None,
+            selected_text: None,
+            clip: false,
             handlers: Handlers::default(),
             state: Box::new(()),
         }
     }
 
-    pub fn tag(&mut self, tag: usize) -> bool {
+    pub fn tag(&mut self, tag: Id) -> bool {
         if tag == self.tag {
             true
         } else {
+            let key = self.key;
             *self = Node::with_tag(tag);
+            self.key = key;
             false
         }
     }
 
+    pub fn key(&self) -> Option<u64> {
+        self.key
+    }
+
     pub fn shape(&self) -> &Shape {
         &self.shape
     }
@@ -166,6 +614,16 @@ impl Node {
         self.size.y = height;
     }
 
+    /// Returns the distance from the top of this node to the baseline of its first line of
+    /// text, if it has one.
+    pub fn baseline(&self) -> Option<f32> {
+        self.baseline
+    }
+
+    pub fn set_baseline(&mut self, baseline: Option<f32>) {
+        self.baseline = baseline;
+    }
+
     pub fn offset(&self) -> (f32, f32) {
         (self.offset.x, self.offset.y)
     }
@@ -175,6 +633,49 @@ impl Node {
         self.offset.y = y;
     }
 
+    /// Returns the position of this node relative to the root, as of the most recent call to
+    /// `Dispatcher::input`.
+    pub fn position(&self) -> (f32, f32) {
+        (self.position.x, self.position.y)
+    }
+
+    fn update_position(&mut self, offset: Vec2) {
+        self.position = offset + self.offset;
+        let position = self.position;
+        for child in self.children.iter_mut() {
+            child.update_position(position);
+        }
+    }
+
+    /// Asks the nearest enclosing scroll container to bring the given rectangle, in this node's
+    /// coordinates, into view.
+    pub fn scroll_into_view(&mut self, x: f32, y: f32, width: f32, height: f32) {
+        self.scroll_request = Some((Vec2::new(x, y), Vec2::new(width, height)));
+    }
+
+    /// Removes and returns the most recent scroll request made by this node or any of its
+    /// descendants, translated into this node's coordinates.
+    pub fn take_scroll_request(&mut self) -> Option<(Vec2, Vec2)> {
+        let mut request = self.scroll_request.take();
+        for child in self.children.iter_mut() {
+            if let Some((position, size)) = child.take_scroll_request() {
+                request = Some((child.offset + position, size));
+            }
+        }
+        request
+    }
+
+    pub fn clip(&self) -> bool {
+        self.clip
+    }
+
+    /// Sets whether this node's children are clipped to its bounds, both when rendering and
+    /// when hit-testing mouse input. Clipping is approximate when rendering text and paths;
+    /// see `render`.
+    pub fn set_clip(&mut self, clip: bool) {
+        self.clip = clip;
+    }
+
     pub fn children(&self) -> &[Node] {
         &self.children
     }
@@ -195,147 +696,759 @@ impl Node {
         self.state.downcast_mut::<T>().unwrap()
     }
 
-    pub fn on_mouse_move(&mut self, f: impl Fn(&InputState) + 'static) {
+    pub fn on_mouse_move(&mut self, f: impl Fn(&InputState, &Event) + 'static) {
         self.handlers.on_mouse_move = Some(Box::new(f));
     }
 
-    pub fn on_mouse_down(&mut self, f: impl Fn(MouseButton, &InputState) + 'static) {
+    pub fn on_mouse_down(&mut self, f: impl Fn(MouseButton, &InputState, &Event) + 'static) {
         self.handlers.on_mouse_down = Some(Box::new(f));
     }
 
-    pub fn on_mouse_up(&mut self, f: impl Fn(MouseButton, &InputState) + 'static) {
+    pub fn on_mouse_up(&mut self, f: impl Fn(MouseButton, &InputState, &Event) + 'static) {
         self.handlers.on_mouse_up = Some(Box::new(f));
     }
 
-    pub fn on_scroll(&mut self, f: impl Fn(f32, f32, &InputState) + 'static) {
+    pub fn on_scroll(&mut self, f: impl Fn(f32, f32, &InputState, &Event) + 'static) {
         self.handlers.on_scroll = Some(Box::new(f));
     }
 
-    pub fn on_key_down(&mut self, f: impl Fn(Key, &InputState) + 'static) {
+    /// Like `on_mouse_move`, but called on the way down to the target, before any descendant
+    /// sees the event.
+    pub fn on_mouse_move_capture(&mut self, f: impl Fn(&InputState, &Event) + 'static) {
+        self.handlers.on_mouse_move_capture = Some(Box::new(f));
+    }
+
+    pub fn on_mouse_down_capture(&mut self, f: impl Fn(MouseButton, &InputState, &Event) + 'static) {
+        self.handlers.on_mouse_down_capture = Some(Box::new(f));
+    }
+
+    pub fn on_mouse_up_capture(&mut self, f: impl Fn(MouseButton, &InputState, &Event) + 'static) {
+        self.handlers.on_mouse_up_capture = Some(Box::new(f));
+    }
+
+    pub fn on_scroll_capture(&mut self, f: impl Fn(f32, f32, &InputState, &Event) + 'static) {
+        self.handlers.on_scroll_capture = Some(Box::new(f));
+    }
+
+    /// Called when the mouse starts hovering this node. Unlike other mouse events, enter and
+    /// leave events are delivered to every node whose hover state changes and don't bubble.
+    pub fn on_mouse_enter(&mut self, f: impl Fn(&InputState) + 'static) {
+        self.handlers.on_mouse_enter = Some(Box::new(f));
+    }
+
+    pub fn on_mouse_leave(&mut self, f: impl Fn(&InputState) + 'static) {
+        self.handlers.on_mouse_leave = Some(Box::new(f));
+    }
+
+    /// Called when a mouse button is pressed and released over this node, or when it is
+    /// pressed over one descendant and released over another. The handler receives the click
+    /// count, which is 2 for a double click, 3 for a triple click and so on. A press that a
+    /// `GestureDetector` recognizes as a pan or long press doesn't produce a click.
+    pub fn on_click(&mut self, f: impl Fn(MouseButton, u32, &InputState, &Event) + 'static) {
+        self.handlers.on_click = Some(Box::new(f));
+    }
+
+    pub fn on_key_down(&mut self, f: impl Fn(Key, &InputState, &Event) + 'static) {
         self.handlers.on_key_down = Some(Box::new(f));
     }
 
-    pub fn on_key_up(&mut self, f: impl Fn(Key, &InputState) + 'static) {
+    pub fn on_key_up(&mut self, f: impl Fn(Key, &InputState, &Event) + 'static) {
         self.handlers.on_key_up = Some(Box::new(f));
     }
 
-    pub fn on_char(&mut self, f: impl Fn(char, &InputState) + 'static) {
+    pub fn on_char(&mut self, f: impl Fn(char, &InputState, &Event) + 'static) {
         self.handlers.on_char = Some(Box::new(f));
     }
 
-    pub fn hover(&self) -> bool {
-        self.hover
+    pub fn on_cut(&mut self, f: impl Fn(&InputState) + 'static) {
+        self.handlers.on_cut = Some(Box::new(f));
     }
 
-    pub fn dragging(&self) -> bool {
-        self.dragging
+    pub fn on_paste(&mut self, f: impl Fn(&str, &InputState) + 'static) {
+        self.handlers.on_paste = Some(Box::new(f));
     }
 
-    pub fn render(&self, frame: &mut Frame) {
-        self.render_inner(frame, Vec2::new(0.0, 0.0))
+    pub fn selected_text(&self) -> Option<&str> {
+        self.selected_text.as_ref().map(|text| &text[..])
     }
 
-    fn render_inner(&self, frame: &mut Frame, offset: Vec2) {
-        let offset = offset + self.offset;
+    /// Exposes the text currently selected in this node to the clipboard.
+    pub fn set_selected_text(&mut self, selected_text: Option<String>) {
+        self.selected_text = selected_text;
+    }
 
-        match &self.shape {
-            Shape::Empty => {}
-            Shape::Rect { position, dimensions, color } => {
-                frame.draw_rect(offset + *position, *dimensions, Mat2x2::id(), *color);
-            }
-            Shape::Text { position, font, size, glyphs, color } => {
-                frame.draw_text(font, *size, glyphs, offset + *position, Mat2x2::id(), *color);
-            }
-            Shape::Path { position, path, color } => {
-                frame.draw_path(path, offset + *position, Mat2x2::id(), *color);
+    /// Returns the selected text of the focused node or its nearest ancestor with a selection.
+    pub fn copy(&self) -> Option<String> {
+        let path = self.focus_path().unwrap_or_default();
+        (0..=path.len()).rev()
+            .filter_map(|depth| self.descendant(&path[..depth]).selected_text.clone())
+            .next()
+    }
+
+    /// Like `copy`, but also asks the node owning the selection to delete it.
+    pub fn cut(&mut self, input_state: &InputState) -> Option<String> {
+        let path = self.focus_path().unwrap_or_default();
+        for depth in (0..=path.len()).rev() {
+            let node = self.descendant(&path[..depth]);
+            if let (Some(text), Some(on_cut)) = (&node.selected_text, &node.handlers.on_cut) {
+                on_cut(input_state);
+                return Some(text.clone());
             }
         }
+        None
+    }
 
-        for child in self.children.iter() {
-            child.render_inner(frame, offset);
+    /// Passes `text` to the focused node or its nearest ancestor that accepts pastes. Returns
+    /// `false` if no node accepted it.
+    pub fn paste(&mut self, text: &str, input_state: &InputState) -> bool {
+        let path = self.focus_path().unwrap_or_default();
+        for depth in (0..=path.len()).rev() {
+            if let Some(ref on_paste) = self.descendant(&path[..depth]).handlers.on_paste {
+                on_paste(text, input_state);
+                return true;
+            }
         }
+        false
     }
 
-    pub fn input(&mut self, input: Input, input_state: &InputState) {
-        self.input_inner(input, input_state, Vec2::new(0.0, 0.0));
+    /// Whether the topmost node under the mouse is this node or one of its descendants.
+    pub fn hover(&self) -> bool {
+        self.hover
     }
 
-    fn input_inner(&mut self, input: Input, input_state: &InputState, offset: Vec2) {
-        let offset = offset + self.offset;
+    /// Whether this node has captured the pointer. See `Event::capture_pointer`.
+    pub fn dragging(&self) -> bool {
+        self.dragging
+    }
 
-        match input {
-            Input::MouseMove => {
-                let hover =
-                    input_state.mouse_x >= offset.x && input_state.mouse_x < offset.x + self.size.x &&
-                    input_state.mouse_y >= offset.y && input_state.mouse_y < offset.y + self.size.y;
+    pub fn focusable(&self) -> bool {
+        self.focusable
+    }
 
-                if self.dragging || self.hover || hover {
-                    if let Some(ref on_mouse_move) = self.handlers.on_mouse_move {
-                        on_mouse_move(input_state);
-                    }
-                    for child in self.children.iter_mut() {
-                        child.input_inner(input, input_state, offset);
-                    }
-                }
+    pub fn set_focusable(&mut self, focusable: bool) {
+        self.focusable = focusable;
+    }
 
-                self.hover = hover;
-            }
-            Input::MouseDown(button) => {
-                if self.hover {
-                    self.dragging = true;
+    pub fn tab_index(&self) -> i32 {
+        self.tab_index
+    }
 
-                    if let Some(ref on_mouse_down) = self.handlers.on_mouse_down {
-                        on_mouse_down(button, input_state);
-                    }
-                    for child in self.children.iter_mut() {
-                        child.input_inner(input, input_state, offset);
-                    }
-                }
-            }
-            Input::MouseUp(button) => {
-                if self.hover || self.dragging {
-                    if let Some(ref on_mouse_up) = self.handlers.on_mouse_up {
-                        on_mouse_up(button, input_state);
-                    }
-                    for child in self.children.iter_mut() {
-                        child.input_inner(input, input_state, offset);
-                    }
-                }
+    /// Sets this node's position in Tab order. Nodes with a positive index are visited first,
+    /// in ascending order, followed by nodes with an index of zero in tree order. Nodes with a
+    /// negative index can still be focused by clicking or `request_focus`, but are skipped by
+    /// Tab.
+    pub fn set_tab_index(&mut self, tab_index: i32) {
+        self.tab_index = tab_index;
+    }
 
-                self.dragging = false;
-            }
-            Input::Scroll(dx, dy) => {
-                if self.hover || self.dragging {
-                    if let Some(ref on_scroll) = self.handlers.on_scroll {
-                        on_scroll(dx, dy, input_state);
-                    }
-                    for child in self.children.iter_mut() {
-                        child.input_inner(input, input_state, offset);
-                    }
-                }
-            }
-            Input::KeyDown(..) | Input::KeyUp(..) | Input::Char(..) => {}
-        }
+    pub fn focus_scope(&self) -> bool {
+        self.focus_scope
     }
-}
 
-pub struct Receiver<T> {
-    queue: Rc<Cell<Vec<T>>>,
-}
+    /// Marks this node as a focus scope. While focus is inside a scope, Tab and Shift+Tab only
+    /// cycle between the focusable nodes within it.
+    pub fn set_focus_scope(&mut self, focus_scope: bool) {
+        self.focus_scope = focus_scope;
+    }
 
-impl<T: 'static> Receiver<T> {
-    pub fn new() -> Receiver<T> {
-        Receiver { queue: Rc::new(Cell::new(Vec::new())) }
+    pub fn focused(&self) -> bool {
+        self.focused
     }
 
-    pub fn sender(&self) -> Sender<T> {
-        Sender { queue: self.queue.clone() }
+    /// Requests keyboard focus for this node. The request takes effect on the next call to
+    /// `update_focus` (or `Dispatcher::input`), at which point any previously focused node loses
+    /// focus.
+    pub fn request_focus(&mut self) {
+        self.focus_requested = true;
     }
 
-    pub fn poll(&self) -> impl Iterator<Item=T> {
-        self.queue.replace(Vec::new()).into_iter()
+    pub fn release_focus(&mut self) {
+        self.focused = false;
+        self.focus_requested = false;
     }
-}
+
+    /// Resolves pending focus requests in the tree rooted at this node. If several nodes
+    /// requested focus, the last one in tree order wins.
+    pub fn update_focus(&mut self) {
+        if let Some(path) = self.focus_request_path() {
+            self.set_focus_path(Some(&path));
+        }
+    }
+
+    fn focus_request_path(&mut self) -> Option<Vec<usize>> {
+        let mut result = None;
+        if self.focus_requested {
+            self.focus_requested = false;
+            result = Some(Vec::new());
+        }
+        for (i, child) in self.children.iter_mut().enumerate() {
+            if let Some(mut path) = child.focus_request_path() {
+                path.insert(0, i);
+                result = Some(path);
+            }
+        }
+        result
+    }
+
+    fn focus_path(&self) -> Option<Vec<usize>> {
+        if self.focused {
+            return Some(Vec::new());
+        }
+        for (i, child) in self.children.iter().enumerate() {
+            if let Some(mut path) = child.focus_path() {
+                path.insert(0, i);
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    fn set_focus_path(&mut self, path: Option<&[usize]>) {
+        self.clear_focus();
+        if let Some(path) = path {
+            self.descendant_mut(path).focused = true;
+        }
+    }
+
+    fn clear_focus(&mut self) {
+        self.focused = false;
+        for child in self.children.iter_mut() {
+            child.clear_focus();
+        }
+    }
+
+    /// Moves focus to the next (or, if `backward`, the previous) node in Tab order, staying
+    /// within the innermost focus scope containing the currently focused node.
+    fn traverse_focus(&mut self, backward: bool) {
+        let focus_path = self.focus_path();
+
+        let mut scope_path = Vec::new();
+        if let Some(ref focus_path) = focus_path {
+            for depth in 0..focus_path.len() {
+                if self.descendant(&focus_path[..depth]).focus_scope {
+                    scope_path = focus_path[..depth].to_vec();
+                }
+            }
+        }
+
+        let mut candidates = Vec::new();
+        self.descendant(&scope_path).tab_candidates(&mut scope_path.clone(), &mut candidates);
+        candidates.sort_by_key(|&(tab_index, _)| if tab_index > 0 { tab_index } else { std::i32::MAX });
+        if candidates.is_empty() {
+            return;
+        }
+
+        let current = focus_path.and_then(|focus_path| {
+            candidates.iter().position(|(_, path)| *path == focus_path)
+        });
+        let next = match (current, backward) {
+            (Some(i), false) => (i + 1) % candidates.len(),
+            (Some(i), true) => (i + candidates.len() - 1) % candidates.len(),
+            (None, false) => 0,
+            (None, true) => candidates.len() - 1,
+        };
+
+        let path = candidates.swap_remove(next).1;
+        self.set_focus_path(Some(&path));
+    }
+
+    fn tab_candidates(&self, path: &mut Vec<usize>, candidates: &mut Vec<(i32, Vec<usize>)>) {
+        if self.focusable && self.tab_index >= 0 {
+            candidates.push((self.tab_index, path.clone()));
+        }
+        for (i, child) in self.children.iter().enumerate() {
+            path.push(i);
+            child.tab_candidates(path, candidates);
+            path.pop();
+        }
+    }
+
+    /// Returns the path to the innermost focusable node containing the node at `path`.
+    fn focusable_ancestor_path(&self, path: &[usize]) -> Option<Vec<usize>> {
+        (0..=path.len()).rev()
+            .find(|&depth| self.descendant(&path[..depth]).focusable)
+            .map(|depth| path[..depth].to_vec())
+    }
+
+    pub fn release_pointer(&mut self) {
+        self.dragging = false;
+    }
+
+    fn capture_path(&self) -> Option<Vec<usize>> {
+        if self.dragging {
+            return Some(Vec::new());
+        }
+        for (i, child) in self.children.iter().enumerate() {
+            if let Some(mut path) = child.capture_path() {
+                path.insert(0, i);
+                return Some(path);
+            }
+        }
+        None
+    }
+
+    fn clear_dragging(&mut self) {
+        self.dragging = false;
+        for child in self.children.iter_mut() {
+            child.clear_dragging();
+        }
+    }
+
+    fn descendant(&self, path: &[usize]) -> &Node {
+        path.iter().fold(self, |node, &i| &node.children[i])
+    }
+
+    fn descendant_mut(&mut self, path: &[usize]) -> &mut Node {
+        path.iter().fold(self, |node, &i| &mut node.children[i])
+    }
+
+    /// Draws the tree rooted at this node.
+    ///
+    /// Gouache can't restrict drawing to a rectangle, so clipping (see `set_clip`) is only
+    /// approximated: rectangles are cut to the clip rectangle, but glyphs and paths, which
+    /// can't be cut, are drawn only if they lie entirely inside it. Text and paths that cross
+    /// the edge of a clipping node are therefore hidden piece by piece rather than cut off,
+    /// while rounded rectangles lose their rounded corners.
+    pub fn render(&self, frame: &mut Frame) {
+        self.render_inner(frame, Vec2::new(0.0, 0.0), None)
+    }
+
+    fn render_inner(&self, frame: &mut Frame, offset: Vec2, clip: Option<ClipRect>) {
+        let offset = offset + self.offset;
+
+        match &self.shape {
+            Shape::Empty => {}
+            Shape::Rect { position, dimensions, color } => {
+                let rect = ClipRect::new(offset + *position, *dimensions);
+                if let Some(rect) = rect.intersect(clip) {
+                    frame.draw_rect(rect.min, rect.max - rect.min, Mat2x2::id(), *color);
+                }
+            }
+            Shape::Text { position, font, size, glyphs, advances, color } => {
+                let origin = offset + *position;
+                let ascent = edit::baseline(font, *size);
+                let line_height = edit::line_height(font, *size);
+                let inside = |glyph: &Glyph, advance: f32| clip.map_or(true, |clip| {
+                    let top_left = origin + glyph.position - Vec2::new(0.0, ascent);
+                    clip.contains_rect(ClipRect::new(top_left, Vec2::new(advance, line_height)))
+                });
+
+                // The glyphs are only copied when some of them cross the edge of the clip.
+                if glyphs.iter().zip(advances.iter()).all(|(glyph, &advance)| inside(glyph, advance)) {
+                    frame.draw_text(font, *size, glyphs, origin, Mat2x2::id(), *color);
+                } else {
+                    let glyphs: Vec<Glyph> = glyphs.iter().zip(advances.iter())
+                        .filter(|&(glyph, &advance)| inside(glyph, advance))
+                        .map(|(glyph, _)| glyph.clone())
+                        .collect();
+                    frame.draw_text(font, *size, &glyphs, origin, Mat2x2::id(), *color);
+                }
+            }
+            Shape::Path { position, path, color } => {
+                // Paths are assumed to lie within their node's bounds.
+                if clip.map_or(true, |clip| clip.contains_rect(ClipRect::new(offset, self.size))) {
+                    frame.draw_path(path, offset + *position, Mat2x2::id(), *color);
+                }
+            }
+            Shape::RoundedRect { position, dimensions, path, color } => {
+                let rect = ClipRect::new(offset + *position, *dimensions);
+                if clip.map_or(true, |clip| clip.contains_rect(rect)) {
+                    frame.draw_path(path, offset + *position, Mat2x2::id(), *color);
+                } else if let Some(rect) = rect.intersect(clip) {
+                    frame.draw_rect(rect.min, rect.max - rect.min, Mat2x2::id(), *color);
+                }
+            }
+        }
+
+        let clip = if self.clip {
+            match ClipRect::new(offset, self.size).intersect(clip) {
+                Some(clip) => Some(clip),
+                None => return,
+            }
+        } else {
+            clip
+        };
+
+        for child in self.children.iter() {
+            child.render_inner(frame, offset, clip);
+        }
+    }
+
+    /// Delivers a click to the node at `path` and then to each of its ancestors in turn.
+    fn dispatch_click(&self, path: &[usize], button: MouseButton, count: u32, input_state: &InputState) {
+        let event = Event::new();
+        for depth in (0..=path.len()).rev() {
+            event.set_phase(if depth == path.len() { Phase::Target } else { Phase::Bubble });
+            if let Some(ref on_click) = self.descendant(&path[..depth]).handlers.on_click {
+                on_click(button, count, input_state, &event);
+            }
+            if event.propagation_stopped() {
+                break;
+            }
+        }
+    }
+
+    /// Delivers an event to the nodes along `path`: to capture handlers from the root down to
+    /// the target, then to regular handlers from the target back up to the root.
+    fn dispatch(&mut self, path: &[usize], input: Input, input_state: &InputState, event: Event) -> Event {
+        for depth in 0..=path.len() {
+            event.set_phase(if depth == path.len() { Phase::Target } else { Phase::Capture });
+            self.descendant(&path[..depth]).handlers.call(input, input_state, &event, true);
+            self.handle_capture_request(&path[..depth], &event);
+            if event.propagation_stopped() {
+                return event;
+            }
+        }
+
+        for depth in (0..=path.len()).rev() {
+            event.set_phase(if depth == path.len() { Phase::Target } else { Phase::Bubble });
+            self.descendant(&path[..depth]).handlers.call(input, input_state, &event, false);
+            self.handle_capture_request(&path[..depth], &event);
+            if event.propagation_stopped() {
+                return event;
+            }
+        }
+
+        event
+    }
+
+    fn handle_capture_request(&mut self, path: &[usize], event: &Event) {
+        match event.take_capture_request() {
+            Some(true) => {
+                self.clear_dragging();
+                self.descendant_mut(path).dragging = true;
+            }
+            Some(false) => {
+                self.descendant_mut(path).dragging = false;
+            }
+            None => {}
+        }
+    }
+
+    /// Returns the path to the topmost node under the mouse, or `None` if there is none.
+    /// Nodes are tested in reverse paint order, so later siblings are tested before earlier
+    /// ones and children before their parent, and nodes outside the clip rectangle of a
+    /// clipping ancestor are skipped. Positions must be up to date.
+    fn hit_path(&self, mouse: Vec2, clip: ClipRect) -> Option<Vec<usize>> {
+        let rect = ClipRect::new(self.position, self.size);
+
+        let child_clip = if self.clip { rect.intersect(Some(clip)).unwrap_or(ClipRect::EMPTY) } else { clip };
+        for (i, child) in self.children.iter().enumerate().rev() {
+            if let Some(mut path) = child.hit_path(mouse, child_clip) {
+                path.insert(0, i);
+                return Some(path);
+            }
+        }
+
+        if rect.contains(mouse) && clip.contains(mouse) {
+            Some(Vec::new())
+        } else {
+            None
+        }
+    }
+
+    fn hover_path(&self) -> Option<Vec<usize>> {
+        if !self.hover {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut node = self;
+        while let Some(i) = node.children.iter().position(|child| child.hover) {
+            path.push(i);
+            node = &node.children[i];
+        }
+        Some(path)
+    }
+
+    /// Marks the node at `path` and all of its ancestors as hovered, and every other node as
+    /// not hovered, calling the leave handlers of nodes that are no longer hovered from the
+    /// innermost out and then the enter handlers of newly hovered nodes from the outermost in.
+    fn set_hover_path(&mut self, path: Option<&[usize]>, input_state: &InputState) {
+        let old_path = self.hover_path();
+        let unchanged = match (&old_path, path) {
+            (Some(old_path), Some(path)) => {
+                old_path.iter().zip(path.iter()).take_while(|(a, b)| a == b).count() + 1
+            }
+            _ => 0,
+        };
+
+        if let Some(ref old_path) = old_path {
+            for depth in (unchanged..=old_path.len()).rev() {
+                if let Some(ref on_mouse_leave) = self.descendant(&old_path[..depth]).handlers.on_mouse_leave {
+                    on_mouse_leave(input_state);
+                }
+            }
+        }
+
+        self.clear_hover();
+        if let Some(path) = path {
+            for depth in 0..=path.len() {
+                self.descendant_mut(&path[..depth]).hover = true;
+            }
+            for depth in unchanged..=path.len() {
+                if let Some(ref on_mouse_enter) = self.descendant(&path[..depth]).handlers.on_mouse_enter {
+                    on_mouse_enter(input_state);
+                }
+            }
+        }
+    }
+
+    fn clear_hover(&mut self) {
+        self.hover = false;
+        for child in self.children.iter_mut() {
+            child.clear_hover();
+        }
+    }
+}
+
+/// Routes input from a backend to a tree of nodes, keeping the state that spans several
+/// inputs, such as the last press for counting clicks. A backend owns one alongside its root
+/// node.
+#[derive(Default)]
+pub struct Dispatcher {
+    click_settings: ClickSettings,
+    /// The target of the press of each held button that was pressed over a node.
+    pressed: Vec<(MouseButton, Vec<usize>)>,
+    last_press: Option<(MouseButton, Instant, Vec2)>,
+    click_count: u32,
+    /// The buttons currently held, in the order they were pressed, each with the gesture arena
+    /// opened for its press.
+    held: Vec<(MouseButton, GestureArena)>,
+    /// The button whose release ends the current pointer capture, if any.
+    capture_button: Option<MouseButton>,
+    /// Whether the mouse is in the window, i.e. has moved since it last left.
+    mouse_inside: bool,
+}
+
+impl Dispatcher {
+    pub fn new() -> Dispatcher {
+        Dispatcher::default()
+    }
+
+    pub fn click_settings(&self) -> ClickSettings {
+        self.click_settings
+    }
+
+    /// Sets the thresholds for double and triple clicks.
+    pub fn set_click_settings(&mut self, click_settings: ClickSettings) {
+        self.click_settings = click_settings;
+    }
+
+    /// Dispatches an input event to the tree rooted at `root`.
+    ///
+    /// Mouse events are targeted at the topmost node under the mouse, except that mouse move
+    /// and mouse up events go to the node that has captured the pointer, if any; the capture
+    /// ends when the button it was made for is released (see `Event::capture_pointer`).
+    /// Keyboard events are targeted at the focused node, or at the root if no node is focused.
+    /// The event is first delivered to the capture handlers on the way from the root down to
+    /// the target, then to the regular handlers on the way back up, until a handler stops its
+    /// propagation.
+    pub fn input(&mut self, root: &mut Node, input: Input, input_state: &InputState) {
+        root.update_focus();
+        root.update_position(Vec2::new(0.0, 0.0));
+        let mouse = Vec2::new(input_state.mouse_x, input_state.mouse_y);
+
+        match input {
+            Input::MouseMove => {
+                self.mouse_inside = true;
+                let hit_path = root.hit_path(mouse, ClipRect::EVERYTHING);
+                root.set_hover_path(hit_path.as_ref().map(|path| &path[..]), input_state);
+                if let Some(path) = root.capture_path().or(hit_path) {
+                    self.dispatch(root, &path, input, input_state, Event::new());
+                }
+            }
+            Input::MouseLeave => {
+                self.mouse_inside = false;
+                root.set_hover_path(None, input_state);
+            }
+            Input::MouseDown(button) => {
+                let path = root.hit_path(mouse, ClipRect::EVERYTHING);
+                root.set_hover_path(path.as_ref().map(|path| &path[..]), input_state);
+                self.press(button, mouse, path.clone());
+                // Each button gets its own arena, so that pressing another button doesn't end a
+                // gesture in progress.
+                self.release(button);
+                let arena = GestureArena::new();
+                self.held.push((button, arena.clone()));
+                let event = match path {
+                    Some(ref path) => self.dispatch(root, path, input, input_state, Event::new().with_arena(arena)),
+                    None => Event::new(),
+                };
+                if !event.default_prevented() {
+                    let focus = path.and_then(|path| root.focusable_ancestor_path(&path));
+                    root.set_focus_path(focus.as_ref().map(|path| &path[..]));
+                }
+            }
+            Input::MouseUp(button) => {
+                let hit_path = root.hit_path(mouse, ClipRect::EVERYTHING);
+                if let Some(path) = root.capture_path().or_else(|| hit_path.clone()) {
+                    self.dispatch(root, &path, input, input_state, Event::new());
+                }
+                // A press that a gesture detector recognized as a pan or long press isn't a click.
+                let claimed = self.held.iter().any(|&(held, ref arena)| held == button && arena.claimed());
+                self.release(button);
+                if self.capture_button.map_or(true, |capture_button| capture_button == button) {
+                    root.clear_dragging();
+                    self.capture_button = None;
+                }
+
+                let pressed = self.pressed.iter().position(|&(pressed, _)| pressed == button)
+                    .map(|index| self.pressed.remove(index).1);
+                if let (Some(pressed_path), Some(hit_path)) = (pressed, hit_path) {
+                    if !claimed {
+                        let common = pressed_path.iter().zip(hit_path.iter()).take_while(|(a, b)| a == b).count();
+                        root.dispatch_click(&hit_path[..common], button, self.click_count, input_state);
+                    }
+                }
+            }
+            Input::Scroll(..) => {
+                let hit_path = root.hit_path(mouse, ClipRect::EVERYTHING);
+                root.set_hover_path(hit_path.as_ref().map(|path| &path[..]), input_state);
+                if let Some(path) = hit_path {
+                    self.dispatch(root, &path, input, input_state, Event::new());
+                }
+            }
+            Input::KeyDown(..) | Input::KeyUp(..) | Input::Char(..) => {
+                let path = root.focus_path().unwrap_or_default();
+                let event = self.dispatch(root, &path, input, input_state, Event::new());
+                if let Input::KeyDown(Key::Tab) = input {
+                    if !event.default_prevented() {
+                        root.traverse_focus(input_state.modifiers.shift);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Recomputes which nodes are under the mouse. Content can move under a stationary mouse,
+    /// e.g. when it scrolls or is laid out differently, so backends call this after each
+    /// `apply`.
+    pub fn update_hover(&mut self, root: &mut Node, input_state: &InputState) {
+        if !self.mouse_inside {
+            return;
+        }
+
+        root.update_position(Vec2::new(0.0, 0.0));
+        let mouse = Vec2::new(input_state.mouse_x, input_state.mouse_y);
+        let hit_path = root.hit_path(mouse, ClipRect::EVERYTHING);
+        root.set_hover_path(hit_path.as_ref().map(|path| &path[..]), input_state);
+    }
+
+    /// Dispatches an event along `path`, noting which button a pointer capture made by its
+    /// handlers belongs to: the button being pressed, or else the most recently pressed one
+    /// that is still held.
+    fn dispatch(&mut self, root: &mut Node, path: &[usize], input: Input, input_state: &InputState, event: Event) -> Event {
+        let old_capture = root.capture_path();
+        let event = root.dispatch(path, input, input_state, event);
+        let capture = root.capture_path();
+        if capture.is_none() {
+            self.capture_button = None;
+        } else if capture != old_capture {
+            self.capture_button = match input {
+                Input::MouseDown(button) => Some(button),
+                _ => self.held.last().map(|&(button, _)| button),
+            };
+        }
+        event
+    }
+
+    /// Forgets that `button` is held, cancelling the gestures still waiting for its release.
+    fn release(&mut self, button: MouseButton) {
+        if let Some(index) = self.held.iter().position(|&(held, _)| held == button) {
+            let (_, arena) = self.held.remove(index);
+            arena.close();
+        }
+    }
+
+    /// Records a mouse press for click detection, counting it as a repeated click if it closely
+    /// follows the previous press.
+    fn press(&mut self, button: MouseButton, mouse: Vec2, path: Option<Vec<usize>>) {
+        let now = Instant::now();
+        let settings = self.click_settings;
+        let repeated = match self.last_press {
+            Some((last_button, time, position)) => {
+                let delta = mouse - position;
+                last_button == button &&
+                    now.duration_since(time) <= settings.interval &&
+                    (delta.x * delta.x + delta.y * delta.y).sqrt() <= settings.distance
+            }
+            None => false,
+        };
+
+        self.click_count = if repeated { self.click_count + 1 } else { 1 };
+        self.last_press = Some((button, now, mouse));
+        self.pressed.retain(|&(pressed, _)| pressed != button);
+        if let Some(path) = path {
+            self.pressed.push((button, path));
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct ClipRect {
+    min: Vec2,
+    max: Vec2,
+}
+
+impl ClipRect {
+    const EMPTY: ClipRect = ClipRect {
+        min: Vec2 { x: 0.0, y: 0.0 },
+        max: Vec2 { x: 0.0, y: 0.0 },
+    };
+    const EVERYTHING: ClipRect = ClipRect {
+        min: Vec2 { x: std::f32::NEG_INFINITY, y: std::f32::NEG_INFINITY },
+        max: Vec2 { x: std::f32::INFINITY, y: std::f32::INFINITY },
+    };
+
+    fn new(position: Vec2, size: Vec2) -> ClipRect {
+        ClipRect { min: position, max: position + size }
+    }
+
+    fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x && point.x < self.max.x &&
+        point.y >= self.min.y && point.y < self.max.y
+    }
+
+    fn contains_rect(&self, rect: ClipRect) -> bool {
+        rect.min.x >= self.min.x && rect.max.x <= self.max.x &&
+        rect.min.y >= self.min.y && rect.max.y <= self.max.y
+    }
+
+    /// Intersects this rectangle with an optional clip rectangle, returning `None` if the
+    /// result is empty.
+    fn intersect(&self, clip: Option<ClipRect>) -> Option<ClipRect> {
+        let clip = match clip {
+            Some(clip) => clip,
+            None => return Some(*self),
+        };
+
+        let min = Vec2::new(self.min.x.max(clip.min.x), self.min.y.max(clip.min.y));
+        let max = Vec2::new(self.max.x.min(clip.max.x), self.max.y.min(clip.max.y));
+        if min.x < max.x && min.y < max.y {
+            Some(ClipRect { min, max })
+        } else {
+            None
+        }
+    }
+}
+
+pub struct Receiver<T> {
+    queue: Rc<Cell<Vec<T>>>,
+}
+
+impl<T: 'static> Receiver<T> {
+    pub fn new() -> Receiver<T> {
+        Receiver { queue: Rc::new(Cell::new(Vec::new())) }
+    }
+
+    pub fn sender(&self) -> Sender<T> {
+        Sender { queue: self.queue.clone() }
+    }
+
+    pub fn poll(&self) -> impl Iterator<Item=T> {
+        self.queue.replace(Vec::new()).into_iter()
+    }
+}
 
 #[derive(Clone)]
 pub struct Sender<T> {
@@ -357,17 +1470,84 @@ pub struct Cursor<'a> {
 
 impl<'a> Cursor<'a> {
     pub fn add(&mut self) -> &mut Node {
-        assert!(self.index <= self.node.children.len());
+        self.add_with_key(None)
+    }
 
-        let child = if self.index == self.node.children.len() {
-            self.node.children.push(Node::new());
-            self.node.children.last_mut().unwrap()
-        } else {
-            &mut self.node.children[self.index]
-        };
+    /// Adds a child matched by `key` rather than by position. If a remaining child from the
+    /// previous frame has the same key, it is moved into place along with all of its state.
+    pub fn add_keyed<K: Hash>(&mut self, key: K) -> &mut Node {
+        self.add_with_key(Some(hash_key(key)))
+    }
+
+    pub fn add_with_key(&mut self, key: Option<u64>) -> &mut Node {
+        let index = self.index;
+        let children = &mut self.node.children;
+        assert!(index <= children.len());
+
+        // Unkeyed children are matched in order, skipping over keyed ones, so that inserting a
+        // keyed child doesn't shift the state of the unkeyed children after it.
+        let found = children[index..].iter().position(|child| child.key == key).map(|i| index + i);
+        match found {
+            Some(found) => {
+                children[index..=found].rotate_right(1);
+            }
+            None => {
+                let mut child = Node::new();
+                child.key = key;
+                children.insert(index, child);
+            }
+        }
 
         self.index += 1;
-        child
+        &mut children[index]
+    }
+
+    /// Adds a child for `elem`, using its key if it has one, and applies `elem` to it.
+    pub fn apply<E: Elem>(&mut self, elem: E, constraints: Constraints) {
+        let key = elem.key();
+        elem.apply(self.add_with_key(key), constraints);
+    }
+}
+
+pub fn hash_key<K: Hash>(key: K) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Gives an element a key, so that its node keeps its state when its position among its
+/// siblings changes. Single-child wrappers such as `Padding` pass the key on, so `Keyed`
+/// needn't be the outermost element.
+pub struct Keyed<C: Elem> {
+    key: u64,
+    child: C,
+}
+
+impl<C: Elem> Keyed<C> {
+    pub fn new<K: Hash>(key: K, child: C) -> Keyed<C> {
+        Keyed { key: hash_key(key), child }
+    }
+}
+
+impl<C: Elem> Elem for Keyed<C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        self.child.apply(node, constraints);
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.child.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.child.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.child.stack_placement()
+    }
+
+    fn key(&self) -> Option<u64> {
+        Some(self.key)
     }
 }
 
@@ -389,6 +1569,8 @@ pub enum Shape {
         font: Rc<Font<'static>>,
         size: f32,
         glyphs: Vec<Glyph>,
+        /// The advance of each glyph, used to tell whether it crosses the edge of a clip.
+        advances: Vec<f32>,
         color: Color,
     },
     Path {
@@ -396,40 +1578,266 @@ pub enum Shape {
         path: Rc<Path>,
         color: Color,
     },
+    /// A rectangle drawn as `path`, which rounds its corners. Where it crosses the edge of a
+    /// clip, it is drawn as a plain rectangle cut to the clip instead.
+    RoundedRect {
+        position: Vec2,
+        dimensions: Vec2,
+        path: Rc<Path>,
+        color: Color,
+    },
+}
+
+/// Returns a `Shape::RoundedRect`, or a plain `Shape::Rect` if `radius` is zero.
+fn rounded_rect(position: Vec2, dimensions: Vec2, radius: f32, color: Color) -> Shape {
+    let radius = radius.min(dimensions.x / 2.0).min(dimensions.y / 2.0);
+    if radius <= 0.0 {
+        return Shape::Rect { position, dimensions, color };
+    }
+
+    // Distance from a corner to the control points of a cubic approximating a quarter circle.
+    let k = radius * (1.0 - 0.5523);
+    let (width, height) = (dimensions.x, dimensions.y);
+    let path = PathBuilder::new()
+        .move_to(radius, 0.0)
+        .line_to(width - radius, 0.0)
+        .cubic_to(width - k, 0.0, width, k, width, radius)
+        .line_to(width, height - radius)
+        .cubic_to(width, height - k, width - k, height, width - radius, height)
+        .line_to(radius, height)
+        .cubic_to(k, height, 0.0, height - k, 0.0, height - radius)
+        .line_to(0.0, radius)
+        .cubic_to(0.0, k, k, 0.0, radius, 0.0)
+        .build();
+    Shape::RoundedRect { position, dimensions, path: Rc::new(path), color }
+}
+
+pub struct Empty;
+
+impl Elem for Empty {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        let (width, height) = constraints.constrain(0.0, 0.0);
+        node.set_size(width, height);
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum TextAlign {
+    Left,
+    Center,
+    Right,
+    /// Stretches the spaces in each line to fill the maximum width allowed by the constraints,
+    /// except in the last line of each paragraph.
+    Justify,
+}
+
+/// A paragraph of text. The text can be any string type, e.g. `&str`, `String`, `Cow<str>` or
+/// `Rc<str>`. It is broken into lines at newlines and, unless disabled with `wrap(false)`,
+/// wrapped to the maximum width allowed by its constraints.
+pub struct Text<T: AsRef<str>> {
+    font: Option<Rc<Font<'static>>>,
+    size: Option<f32>,
+    color: Option<Color>,
+    text: T,
+    align: TextAlign,
+    wrap: bool,
+    line_height: Option<f32>,
+    max_lines: Option<usize>,
 }
 
-pub struct Empty;
+impl<T: AsRef<str>> Text<T> {
+    pub fn new(font: Rc<Font<'static>>, size: f32, text: T) -> Text<T> {
+        Text { font: Some(font), size: Some(size), ..Text::themed(text) }
+    }
+
+    /// Creates text drawn in the font and size of the current `Theme`.
+    pub fn themed(text: T) -> Text<T> {
+        Text {
+            font: None,
+            size: None,
+            color: None,
+            text,
+            align: TextAlign::Left,
+            wrap: true,
+            line_height: None,
+            max_lines: None,
+        }
+    }
+
+    /// Overrides the text color of the current `Theme`.
+    pub fn color(self, color: Color) -> Text<T> {
+        Text { color: Some(color), ..self }
+    }
+
+    pub fn align(self, align: TextAlign) -> Text<T> {
+        Text { align, ..self }
+    }
+
+    pub fn wrap(self, wrap: bool) -> Text<T> {
+        Text { wrap, ..self }
+    }
+
+    /// Sets the distance between consecutive baselines. Defaults to the font's line height.
+    pub fn line_height(self, line_height: f32) -> Text<T> {
+        Text { line_height: Some(line_height), ..self }
+    }
+
+    /// Limits the number of lines shown. Text that doesn't fit is cut off with an ellipsis.
+    pub fn max_lines(self, max_lines: usize) -> Text<T> {
+        Text { max_lines: Some(max_lines.max(1)), ..self }
+    }
+
+    fn ellipsize(&self, font: &Font<'static>, size: f32, line: &str, max_width: f32) -> String {
+        let line = line.trim_end();
+        let ellipsis_width = font.measure("\u{2026}", size).0;
+        let offsets = edit::caret_offsets(font, size, line);
+        let end = offsets.iter().rev()
+            .find(|&&(_, x)| x + ellipsis_width <= max_width)
+            .map_or(0, |&(index, _)| index);
+        format!("{}\u{2026}", line[..end].trim_end())
+    }
+
+    fn layout(&self, font: &Font<'static>, size: f32, text: &str, constraints: Constraints) -> (Vec<Glyph>, Vec<f32>, f32, f32, f32) {
+        let font_line_height = edit::line_height(font, size);
+        let line_height = self.line_height.unwrap_or(font_line_height);
+        let leading = (line_height - font_line_height) / 2.0;
+        let max_width = constraints.max_width();
+
+        let wrap_width = if self.wrap { max_width } else { std::f32::INFINITY };
+        let ranges = edit::wrap_lines(font, size, text, wrap_width);
+        let mut lines: Vec<(String, bool)> = ranges.iter().enumerate().map(|(i, range)| {
+            let ends_paragraph = ranges.get(i + 1).map_or(true, |next| next.start != range.end);
+            (text[range.clone()].to_string(), ends_paragraph)
+        }).collect();
+
+        if let Some(max_lines) = self.max_lines {
+            let truncated = lines.len() > max_lines;
+            lines.truncate(max_lines);
+            let count = lines.len();
+            for (i, line) in lines.iter_mut().enumerate() {
+                let too_wide = font.measure(line.0.trim_end(), size).0 > max_width;
+                if too_wide || (truncated && i == count - 1) {
+                    line.0 = self.ellipsize(font, size, &line.0, max_width);
+                    line.1 = true;
+                }
+            }
+        }
 
-impl Elem for Empty {
-    fn apply(self, node: &mut Node, bounds: Bounds) {}
+        let widths: Vec<f32> = lines.iter().map(|(line, _)| font.measure(line.trim_end(), size).0).collect();
+        let natural_width = widths.iter().cloned().fold(0.0, f32::max);
+        // Justified text fills the available width rather than just the widest line.
+        let box_width = if self.align == TextAlign::Justify && max_width.is_finite() {
+            max_width
+        } else {
+            natural_width.min(max_width).max(constraints.min_width())
+        };
+
+        let mut glyphs = Vec::new();
+        let mut advances = Vec::new();
+        for (i, ((line, ends_paragraph), width)) in lines.iter().zip(widths.iter()).enumerate() {
+            let x = match self.align {
+                TextAlign::Left | TextAlign::Justify => 0.0,
+                TextAlign::Center => (box_width - width) / 2.0,
+                TextAlign::Right => box_width - width,
+            };
+            let y = i as f32 * line_height + leading;
+
+            let mut line_glyphs = font.layout(line, size);
+            advances.extend(edit::advances(&line_glyphs, font.measure(line, size).0));
+            let trimmed = line.trim_end();
+            let gaps = trimmed.chars().filter(|c| c.is_whitespace()).count();
+            let justify = self.align == TextAlign::Justify && !ends_paragraph && gaps > 0 &&
+                line_glyphs.len() == line.chars().count();
+            let gap_width = if justify { (box_width - width) / gaps as f32 } else { 0.0 };
+
+            let mut shift = 0.0;
+            for ((index, c), glyph) in line.char_indices().zip(line_glyphs.iter_mut()) {
+                glyph.position.x += x + shift;
+                glyph.position.y += y;
+                if justify && c.is_whitespace() && index < trimmed.len() {
+                    shift += gap_width;
+                }
+            }
+            glyphs.extend(line_glyphs);
+        }
+
+        let height = lines.len().max(1) as f32 * line_height;
+        let baseline = leading + edit::baseline(font, size);
+        (glyphs, advances, box_width, height, baseline)
+    }
 }
 
-pub struct Text {
+/// The parameters most recently laid out by a `Text`, along with the resulting metrics. The
+/// glyphs themselves are kept in the node's shape.
+struct TextLayout {
     font: Rc<Font<'static>>,
     size: f32,
-    text: &'static str,
-}
-
-impl Text {
-    pub fn new(font: Rc<Font<'static>>, size: f32, text: &'static str) -> Text {
-        Text { font, size, text }
-    }
+    text: String,
+    align: TextAlign,
+    wrap: bool,
+    line_height: Option<f32>,
+    max_lines: Option<usize>,
+    constraints: Constraints,
+    width: f32,
+    height: f32,
+    baseline: f32,
 }
 
-impl Elem for Text {
-    fn apply(self, node: &mut Node, bounds: Bounds) {
-        node.tag(id!());
+impl<T: AsRef<str>> Elem for Text<T> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
+
+        let theme = Theme::current();
+        let font = self.font.clone().unwrap_or_else(|| theme.font.clone());
+        let size = self.size.unwrap_or(theme.text_size);
+        let color = self.color.unwrap_or(theme.text_color);
+
+        let text = self.text.as_ref();
+        let cached = match node.state(|| None::<TextLayout>) {
+            Some(layout) if Rc::ptr_eq(&layout.font, &font) && layout.size == size &&
+                layout.text == text && layout.align == self.align && layout.wrap == self.wrap &&
+                layout.line_height == self.line_height && layout.max_lines == self.max_lines &&
+                layout.constraints == constraints => {
+                Some((layout.width, layout.height, layout.baseline))
+            }
+            _ => None,
+        };
 
-        node.set_shape(Shape::Text {
-            font: self.font.clone(),
-            size: self.size,
-            glyphs: self.font.layout(self.text, self.size),
-            position: Vec2::new(0.0, 0.0),
-            color: Color::rgba(1.0, 1.0, 1.0, 1.0),
-        });
+        let (width, height, baseline) = if let Some(metrics) = cached {
+            if let Shape::Text { color: ref mut shape_color, .. } = node.shape {
+                *shape_color = color;
+            }
+            metrics
+        } else {
+            let (glyphs, advances, width, height, baseline) = self.layout(&font, size, text, constraints);
+            node.set_shape(Shape::Text {
+                font: font.clone(),
+                size,
+                glyphs,
+                advances,
+                position: Vec2::new(0.0, 0.0),
+                color,
+            });
+
+            *node.state(|| None::<TextLayout>) = Some(TextLayout {
+                font,
+                size,
+                text: text.to_string(),
+                align: self.align,
+                wrap: self.wrap,
+                line_height: self.line_height,
+                max_lines: self.max_lines,
+                constraints,
+                width,
+                height,
+                baseline,
+            });
+            (width, height, baseline)
+        };
 
-        let (width, height) = self.font.measure(self.text, self.size);
+        let (width, height) = constraints.constrain(width, height);
         node.set_size(width, height);
+        node.set_baseline(Some(baseline));
     }
 }
 
@@ -445,181 +1853,1011 @@ impl<C: Elem> Padding<C> {
 }
 
 impl<C: Elem> Elem for Padding<C> {
-    fn apply(self, node: &mut Node, bounds: Bounds) {
-        node.tag(id!());
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
 
-        self.child.apply(node.edit_children().add(), Bounds::new(bounds.width - 2.0 * self.padding, bounds.height - 2.0 * self.padding));
+        self.child.apply(node.edit_children().add(), constraints.deflate(2.0 * self.padding, 2.0 * self.padding));
         let mut child = &mut node.children_mut()[0];
         child.set_offset(self.padding, self.padding);
         let (width, height) = child.size();
+        let baseline = child.baseline();
+
+        let (width, height) = constraints.constrain(width + 2.0 * self.padding, height + 2.0 * self.padding);
+        node.set_size(width, height);
+        node.set_baseline(baseline.map(|baseline| baseline + self.padding));
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.child.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.child.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.child.stack_placement()
+    }
 
-        node.set_size(width + 2.0 * self.padding, height + 2.0 * self.padding);
+    fn key(&self) -> Option<u64> {
+        self.child.key()
     }
 }
 
 pub struct BackgroundColor<C: Elem> {
     color: Color,
+    radius: f32,
     child: C,
 }
 
 impl<C: Elem> BackgroundColor<C> {
     pub fn new(color: Color, child: C) -> BackgroundColor<C> {
-        BackgroundColor { color, child }
+        BackgroundColor { color, radius: 0.0, child }
+    }
+
+    pub fn radius(self, radius: f32) -> BackgroundColor<C> {
+        BackgroundColor { radius, ..self }
     }
 }
 
 impl<C: Elem> Elem for BackgroundColor<C> {
-    fn apply(self, node: &mut Node, bounds: Bounds) {
-        node.tag(id!());
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
 
-        self.child.apply(node.edit_children().add(), bounds);
+        self.child.apply(node.edit_children().add(), constraints);
         let (width, height) = node.children()[0].size();
+        let baseline = node.children()[0].baseline();
 
-        node.set_shape(Shape::Rect {
-            position: Vec2::new(0.0, 0.0),
-            dimensions: Vec2::new(width, height),
-            color: self.color,
-        });
+        node.set_shape(rounded_rect(Vec2::new(0.0, 0.0), Vec2::new(width, height), self.radius, self.color));
+        node.set_size(width, height);
+        node.set_baseline(baseline);
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.child.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.child.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.child.stack_placement()
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.child.key()
+    }
+}
+
+pub struct FocusScope<C: Elem> {
+    child: C,
+}
+
+impl<C: Elem> FocusScope<C> {
+    pub fn new(child: C) -> FocusScope<C> {
+        FocusScope { child }
+    }
+}
+
+impl<C: Elem> Elem for FocusScope<C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
+        node.set_focus_scope(true);
+
+        self.child.apply(node.edit_children().add(), constraints);
+        let (width, height) = node.children()[0].size();
+        let baseline = node.children()[0].baseline();
         node.set_size(width, height);
+        node.set_baseline(baseline);
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.child.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.child.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.child.stack_placement()
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.child.key()
     }
 }
 
 pub struct Row<C: ElemList> {
     spacing: f32,
+    justify: Justify,
+    align: Align,
     children: C,
 }
 
 impl<C: ElemList> Row<C> {
     pub fn new(spacing: f32, children: C) -> Row<C> {
-        Row { spacing, children }
+        Row { spacing, justify: Justify::Start, align: Align::Start, children }
     }
-}
 
-impl<C: ElemList> Elem for Row<C> {
-    fn apply(self, node: &mut Node, bounds: Bounds) {
-        node.tag(id!());
+    pub fn justify(self, justify: Justify) -> Row<C> {
+        Row { justify, ..self }
+    }
 
-        self.children.apply_all(&mut node.edit_children(), Bounds::new(std::f32::INFINITY, bounds.height));
+    pub fn align(self, align: Align) -> Row<C> {
+        Row { align, ..self }
+    }
+}
 
-        let mut x: f32 = 0.0;
-        let mut height: f32 = 0.0;
-        for child in node.children_mut() {
-            child.set_offset(x, 0.0);
-            let (child_width, child_height) = child.size();
-            x += child_width + self.spacing;
-            height = height.max(child_height);
-        }
+impl<C: ElemList> Elem for Row<C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
 
-        node.set_size((x - self.spacing).max(0.0), height);
+        let Row { spacing, justify, align, children } = self;
+        children.with_items(&mut |items| {
+            flex_layout(node, items, Axis::Horizontal, spacing, justify, align, constraints);
+        });
     }
 }
 
 pub struct Col<C: ElemList> {
     spacing: f32,
+    justify: Justify,
+    align: Align,
     children: C,
 }
 
 impl<C: ElemList> Col<C> {
     pub fn new(spacing: f32, children: C) -> Col<C> {
-        Col { spacing, children }
+        Col { spacing, justify: Justify::Start, align: Align::Start, children }
     }
-}
 
-impl<C: ElemList> Elem for Col<C> {
-    fn apply(self, node: &mut Node, bounds: Bounds) {
-        node.tag(id!());
+    pub fn justify(self, justify: Justify) -> Col<C> {
+        Col { justify, ..self }
+    }
 
-        self.children.apply_all(&mut node.edit_children(), Bounds::new(std::f32::INFINITY, bounds.height));
+    pub fn align(self, align: Align) -> Col<C> {
+        Col { align, ..self }
+    }
+}
 
-        let mut y: f32 = 0.0;
-        let mut width: f32 = 0.0;
-        for child in node.children_mut() {
-            child.set_offset(0.0, y);
-            let (child_width, child_height) = child.size();
-            y += child_height + self.spacing;
-            width = width.max(child_width);
-        }
+impl<C: ElemList> Elem for Col<C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
 
-        node.set_size(width, (y - self.spacing).max(0.0));
+        let Col { spacing, justify, align, children } = self;
+        children.with_items(&mut |items| {
+            flex_layout(node, items, Axis::Vertical, spacing, justify, align, constraints);
+        });
     }
 }
 
 pub struct Button<C: Elem, F> {
     child: C,
     on_click: F,
+    style: Option<ButtonStyle>,
 }
 
 impl<C: Elem> Button<C, fn()> {
     pub fn new(child: C) -> Button<C, fn()> {
-        Button { child, on_click: || {} }
+        Button { child, on_click: || {}, style: None }
     }
 }
 
 impl<C: Elem, F: Fn()> Button<C, F> {
     pub fn on_click<G: Fn()>(self, on_click: G) -> Button<C, G> {
-        Button { child: self.child, on_click }
+        Button { child: self.child, on_click, style: self.style }
+    }
+
+    /// Overrides the button style of the current `Theme`.
+    pub fn style(self, style: ButtonStyle) -> Button<C, F> {
+        Button { style: Some(style), ..self }
     }
 }
 
 impl<C: Elem, F: Fn() + 'static> Elem for Button<C, F> {
-    fn apply(mut self, node: &mut Node, bounds: Bounds) {
-        node.tag(id!());
+    fn apply(mut self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
+        node.set_focusable(true);
 
-        let on_click = self.on_click;
-        node.on_mouse_up(move |button, input_state| {
+        let on_click = Rc::new(self.on_click);
+        node.on_mouse_down(|button, input_state, event| {
             if button == MouseButton::Left {
+                event.capture_pointer();
+            }
+        });
+        node.on_click({
+            let on_click = on_click.clone();
+            move |button, count, input_state, event| {
+                if button == MouseButton::Left {
+                    on_click();
+                    event.stop_propagation();
+                }
+            }
+        });
+        node.on_key_down(move |key, input_state, event| {
+            if key == Key::Enter || key == Key::Space {
                 on_click();
+                event.stop_propagation();
             }
         });
 
+        let style = self.style.unwrap_or_else(|| Theme::current().button.clone());
         let color = if node.dragging() {
-            Color::rgba(0.141, 0.44, 0.77, 1.0)
+            style.active_color
         } else if node.hover() {
-            Color::rgba(0.54, 0.63, 0.71, 1.0)
+            style.hover_color
         } else {
-            Color::rgba(0.38, 0.42, 0.48, 1.0)
+            style.color
         };
 
-        BackgroundColor::new(color, Padding::new(5.0, self.child))
-            .apply(node.edit_children().add(), bounds);
+        BackgroundColor::new(color, Padding::new(style.padding, self.child))
+            .radius(style.corner_radius)
+            .apply(node.edit_children().add(), constraints);
         let (width, height) = node.children()[0].size();
+        let baseline = node.children()[0].baseline();
         node.set_size(width, height);
+        node.set_baseline(baseline);
+
+        if node.focused() {
+            node.set_shape(rounded_rect(
+                Vec2::new(-2.0, -2.0),
+                Vec2::new(width + 4.0, height + 4.0),
+                style.corner_radius + 2.0,
+                style.focus_color,
+            ));
+        } else {
+            node.set_shape(Shape::Empty);
+        }
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.child.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.child.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.child.stack_placement()
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.child.key()
     }
 }
 
+const SCROLLBAR_WIDTH: f32 = 10.0;
+const MIN_THUMB_LENGTH: f32 = 20.0;
+const SCROLL_LINE: f32 = 48.0;
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+pub enum ScrollbarMode {
+    /// Scrollbars are drawn on top of the content.
+    Overlay,
+    /// Space is reserved next to the content for each scrollable axis.
+    Gutter,
+}
+
 pub struct Scrollable<C: Elem> {
     child: C,
+    horizontal: bool,
+    vertical: bool,
+    scrollbars: ScrollbarMode,
+    style: Option<ScrollbarStyle>,
 }
 
 impl<C: Elem> Scrollable<C> {
     pub fn new(child: C) -> Scrollable<C> {
-        Scrollable { child }
+        Scrollable { child, horizontal: false, vertical: true, scrollbars: ScrollbarMode::Overlay, style: None }
+    }
+
+    pub fn horizontal(self, horizontal: bool) -> Scrollable<C> {
+        Scrollable { horizontal, ..self }
+    }
+
+    pub fn vertical(self, vertical: bool) -> Scrollable<C> {
+        Scrollable { vertical, ..self }
+    }
+
+    pub fn scrollbars(self, scrollbars: ScrollbarMode) -> Scrollable<C> {
+        Scrollable { scrollbars, ..self }
+    }
+
+    /// Overrides the scrollbar style of the current `Theme`.
+    pub fn style(self, style: ScrollbarStyle) -> Scrollable<C> {
+        Scrollable { style: Some(style), ..self }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Axis {
+    Horizontal,
+    Vertical,
+}
+
+struct ScrollAxis {
+    content: f32,
+    view: f32,
+    track: f32,
+}
+
+impl ScrollAxis {
+    fn max_offset(&self) -> f32 {
+        (self.content - self.view).max(0.0)
+    }
+
+    fn thumb(&self, offset: f32) -> (f32, f32) {
+        let length = (self.track * self.view / self.content).max(MIN_THUMB_LENGTH).min(self.track);
+        let max_offset = self.max_offset();
+        let start = if max_offset > 0.0 { (self.track - length) * offset / max_offset } else { 0.0 };
+        (start, length)
     }
+
+    fn thumb_delta_to_offset(&self, delta: f32) -> f32 {
+        let (_, length) = self.thumb(0.0);
+        if self.track > length { delta * self.max_offset() / (self.track - length) } else { 0.0 }
+    }
+}
+
+enum ScrollEvent {
+    Scroll(f32, f32),
+    TrackDown(Axis, f32, f32),
+    TrackMove(f32, f32),
+    TrackUp,
+    KeyDown(Key),
+}
+
+struct ScrollState {
+    offset: Vec2,
+    drag: Option<(Axis, f32, f32)>,
+    rx: Receiver<ScrollEvent>,
 }
 
 impl<C: Elem> Elem for Scrollable<C> {
-    fn apply(mut self, node: &mut Node, bounds: Bounds) {
-        node.tag(id!());
+    fn apply(mut self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
+
+        let style = self.style.take().unwrap_or_else(|| Theme::current().scrollbar.clone());
+        let gutter = if self.scrollbars == ScrollbarMode::Gutter { SCROLLBAR_WIDTH } else { 0.0 };
+        let vertical_gutter = if self.vertical { gutter } else { 0.0 };
+        let horizontal_gutter = if self.horizontal { gutter } else { 0.0 };
+
+        let content_constraints = Constraints::new(
+            0.0,
+            if self.horizontal { std::f32::INFINITY } else { constraints.max_width() - vertical_gutter },
+            0.0,
+            if self.vertical { std::f32::INFINITY } else { constraints.max_height() - horizontal_gutter },
+        );
+
+        let mut children = node.edit_children();
+        let viewport = children.add();
+        viewport.set_clip(true);
+        let (content_width, content_height, scroll_request) = {
+            let mut viewport_children = viewport.edit_children();
+            let content = viewport_children.add();
+            self.child.apply(content, content_constraints);
+            let (content_width, content_height) = content.size();
+            (content_width, content_height, content.take_scroll_request())
+        };
+        children.add();
+        children.add();
+        drop(children);
+
+        let (width, height) = constraints.constrain(content_width + vertical_gutter, content_height + horizontal_gutter);
+        let view_width = width - vertical_gutter;
+        let view_height = height - horizontal_gutter;
+
+        let show_vertical = self.vertical && content_height > view_height;
+        let show_horizontal = self.horizontal && content_width > view_width;
+        let vertical_axis = ScrollAxis {
+            content: content_height,
+            view: view_height,
+            track: height - if show_horizontal { SCROLLBAR_WIDTH } else { 0.0 },
+        };
+        let horizontal_axis = ScrollAxis {
+            content: content_width,
+            view: view_width,
+            track: width - if show_vertical { SCROLLBAR_WIDTH } else { 0.0 },
+        };
 
-        self.child.apply(node.edit_children().add(), Bounds::new(bounds.width, std::f32::INFINITY));
-        let (width, height) = node.children()[0].size();
+        let (x, y) = node.position();
+        let focused = node.focused();
 
-        struct ScrollState {
-            offset: f32,
-            rx: Receiver<f32>,
+        let state = node.state(|| ScrollState {
+            offset: Vec2::new(0.0, 0.0),
+            drag: None,
+            rx: Receiver::new(),
+        });
+        for event in state.rx.poll() {
+            match event {
+                ScrollEvent::Scroll(dx, dy) => {
+                    state.offset.x -= dx;
+                    state.offset.y -= dy;
+                }
+                ScrollEvent::TrackDown(axis, mouse_x, mouse_y) => {
+                    let (scroll_axis, position, offset) = match axis {
+                        Axis::Horizontal => (&horizontal_axis, mouse_x - x, &mut state.offset.x),
+                        Axis::Vertical => (&vertical_axis, mouse_y - y, &mut state.offset.y),
+                    };
+                    let (start, length) = scroll_axis.thumb(*offset);
+                    if position < start {
+                        *offset -= scroll_axis.view;
+                    } else if position >= start + length {
+                        *offset += scroll_axis.view;
+                    } else {
+                        state.drag = Some((axis, position, *offset));
+                    }
+                }
+                ScrollEvent::TrackMove(mouse_x, mouse_y) => {
+                    if let Some((axis, start_position, start_offset)) = state.drag {
+                        match axis {
+                            Axis::Horizontal => {
+                                let delta = horizontal_axis.thumb_delta_to_offset(mouse_x - x - start_position);
+                                state.offset.x = start_offset + delta;
+                            }
+                            Axis::Vertical => {
+                                let delta = vertical_axis.thumb_delta_to_offset(mouse_y - y - start_position);
+                                state.offset.y = start_offset + delta;
+                            }
+                        }
+                    }
+                }
+                ScrollEvent::TrackUp => {
+                    state.drag = None;
+                }
+                ScrollEvent::KeyDown(key) => {
+                    match key {
+                        Key::PageUp => { state.offset.y -= view_height; }
+                        Key::PageDown => { state.offset.y += view_height; }
+                        Key::Up => { state.offset.y -= SCROLL_LINE; }
+                        Key::Down => { state.offset.y += SCROLL_LINE; }
+                        Key::Left => { state.offset.x -= SCROLL_LINE; }
+                        Key::Right => { state.offset.x += SCROLL_LINE; }
+                        Key::Home => { state.offset = Vec2::new(0.0, 0.0); }
+                        Key::End => {
+                            state.offset = Vec2::new(horizontal_axis.max_offset(), vertical_axis.max_offset());
+                        }
+                        _ => {}
+                    }
+                }
+            }
         }
-        let mut state = node.state(|| ScrollState { offset: 0.0, rx: Receiver::new() });
-        for dy in state.rx.poll() {
-            state.offset = (state.offset - dy).min(height - bounds.height).max(0.0);
+        if let Some((position, size)) = scroll_request {
+            if position.x < state.offset.x {
+                state.offset.x = position.x;
+            } else if position.x + size.x > state.offset.x + view_width {
+                state.offset.x = position.x + size.x - view_width;
+            }
+            if position.y < state.offset.y {
+                state.offset.y = position.y;
+            } else if position.y + size.y > state.offset.y + view_height {
+                state.offset.y = position.y + size.y - view_height;
+            }
         }
+        let max_x = if self.horizontal { horizontal_axis.max_offset() } else { 0.0 };
+        let max_y = if self.vertical { vertical_axis.max_offset() } else { 0.0 };
+        state.offset.x = state.offset.x.min(max_x).max(0.0);
+        state.offset.y = state.offset.y.min(max_y).max(0.0);
 
         let tx = state.rx.sender();
         let offset = state.offset;
-        node.on_scroll(move |dx, dy, input_state| {
-            tx.send(dy);
+        let dragging = state.drag.map(|(axis, _, _)| axis);
+
+        // Only take part in Tab order (and click-to-focus) when there is something to scroll.
+        node.set_focusable(max_x > 0.0 || max_y > 0.0);
+
+        node.on_scroll({
+            let tx = tx.clone();
+            move |dx, dy, input_state, event| {
+                let shift = input_state.modifiers.shift;
+                let (dx, dy) = if shift { (dy, dx) } else { (dx, dy) };
+                // Only consume the event if it would actually scroll, so that scrolling past the
+                // end of nested content scrolls the enclosing container instead.
+                let scrolls = (dx > 0.0 && offset.x > 0.0) || (dx < 0.0 && offset.x < max_x) ||
+                    (dy > 0.0 && offset.y > 0.0) || (dy < 0.0 && offset.y < max_y);
+                if scrolls {
+                    tx.send(ScrollEvent::Scroll(dx, dy));
+                    event.stop_propagation();
+                }
+            }
+        });
+        node.on_key_down({
+            let tx = tx.clone();
+            move |key, input_state, event| {
+                // Other keys, such as Escape or shortcuts, are left for the ancestors.
+                let scroll_key = match key {
+                    Key::PageUp | Key::PageDown | Key::Up | Key::Down |
+                    Key::Left | Key::Right | Key::Home | Key::End => true,
+                    _ => false,
+                };
+                if focused && scroll_key {
+                    tx.send(ScrollEvent::KeyDown(key));
+                    event.stop_propagation();
+                }
+            }
         });
 
-        node.children_mut()[0].set_offset(0.0, -offset);
+        let viewport = &mut node.children_mut()[0];
+        viewport.set_size(view_width, view_height);
+        viewport.children_mut()[0].set_offset(-offset.x, -offset.y);
+
+        let scrollbars = [
+            (Axis::Vertical, show_vertical, &vertical_axis, offset.y),
+            (Axis::Horizontal, show_horizontal, &horizontal_axis, offset.x),
+        ];
+        for (i, &(axis, show, scroll_axis, offset)) in scrollbars.iter().enumerate() {
+            let track = &mut node.children_mut()[1 + i];
+
+            let (track_position, track_size) = match axis {
+                Axis::Vertical => (Vec2::new(width - SCROLLBAR_WIDTH, 0.0), Vec2::new(SCROLLBAR_WIDTH, scroll_axis.track)),
+                Axis::Horizontal => (Vec2::new(0.0, height - SCROLLBAR_WIDTH), Vec2::new(scroll_axis.track, SCROLLBAR_WIDTH)),
+            };
+            if !show {
+                track.set_shape(Shape::Empty);
+                track.set_size(0.0, 0.0);
+                drop(track.edit_children());
+                continue;
+            }
+
+            track.set_offset(track_position.x, track_position.y);
+            track.set_size(track_size.x, track_size.y);
+            track.set_shape(rounded_rect(Vec2::new(0.0, 0.0), track_size, style.corner_radius, style.track_color));
+
+            track.on_mouse_down({
+                let tx = tx.clone();
+                move |button, input_state, event| {
+                    if button == MouseButton::Left {
+                        tx.send(ScrollEvent::TrackDown(axis, input_state.mouse_x, input_state.mouse_y));
+                        event.capture_pointer();
+                    }
+                }
+            });
+            track.on_mouse_move({
+                let tx = tx.clone();
+                move |input_state, event| {
+                    tx.send(ScrollEvent::TrackMove(input_state.mouse_x, input_state.mouse_y));
+                }
+            });
+            track.on_mouse_up({
+                let tx = tx.clone();
+                move |button, input_state, event| {
+                    if button == MouseButton::Left {
+                        tx.send(ScrollEvent::TrackUp);
+                    }
+                }
+            });
+
+            let thumb_color = if dragging == Some(axis) {
+                style.thumb_active_color
+            } else if track.hover() {
+                style.thumb_hover_color
+            } else {
+                style.thumb_color
+            };
+            let (start, length) = scroll_axis.thumb(offset);
+            let (thumb_position, thumb_size) = match axis {
+                Axis::Vertical => (Vec2::new(0.0, start), Vec2::new(SCROLLBAR_WIDTH, length)),
+                Axis::Horizontal => (Vec2::new(start, 0.0), Vec2::new(length, SCROLLBAR_WIDTH)),
+            };
+            let mut track_children = track.edit_children();
+            let thumb = track_children.add();
+            thumb.set_offset(thumb_position.x, thumb_position.y);
+            thumb.set_size(thumb_size.x, thumb_size.y);
+            thumb.set_shape(rounded_rect(Vec2::new(0.0, 0.0), thumb_size, style.corner_radius, thumb_color));
+        }
+
         node.set_size(width, height);
     }
+
+    fn flex(&self) -> FlexParams {
+        self.child.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.child.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.child.stack_placement()
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.child.key()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::RefCell;
+
+    use super::*;
+
+    fn press_key(dispatcher: &mut Dispatcher, root: &mut Node, key: Key, shift: bool) {
+        let mut input_state = InputState::default();
+        input_state.modifiers.shift = shift;
+        dispatcher.input(root, Input::KeyDown(key), &input_state);
+        dispatcher.input(root, Input::KeyUp(key), &input_state);
+    }
+
+    #[test]
+    fn tab_visits_positive_indices_first() {
+        let mut root = Node::new();
+        {
+            let mut children = root.edit_children();
+            for &tab_index in [0, 2, -1, 1].iter() {
+                let child = children.add();
+                child.set_focusable(true);
+                child.set_tab_index(tab_index);
+            }
+        }
+
+        let mut dispatcher = Dispatcher::new();
+        let mut order = Vec::new();
+        for _ in 0..4 {
+            press_key(&mut dispatcher, &mut root, Key::Tab, false);
+            order.push(root.focus_path().unwrap());
+        }
+        assert_eq!(order, vec![vec![3], vec![1], vec![0], vec![3]]);
+
+        press_key(&mut dispatcher, &mut root, Key::Tab, true);
+        assert_eq!(root.focus_path(), Some(vec![0]));
+    }
+
+    #[test]
+    fn tab_stays_within_focus_scope() {
+        let mut root = Node::new();
+        {
+            let mut children = root.edit_children();
+            children.add().set_focusable(true);
+            let scope = children.add();
+            scope.set_focus_scope(true);
+            {
+                let mut scope_children = scope.edit_children();
+                scope_children.add().set_focusable(true);
+                scope_children.add().set_focusable(true);
+            }
+            children.add().set_focusable(true);
+        }
+        root.children_mut()[1].children_mut()[0].request_focus();
+
+        let mut dispatcher = Dispatcher::new();
+        press_key(&mut dispatcher, &mut root, Key::Tab, false);
+        assert_eq!(root.focus_path(), Some(vec![1, 1]));
+        press_key(&mut dispatcher, &mut root, Key::Tab, false);
+        assert_eq!(root.focus_path(), Some(vec![1, 0]));
+        press_key(&mut dispatcher, &mut root, Key::Tab, true);
+        assert_eq!(root.focus_path(), Some(vec![1, 1]));
+    }
+
+    #[test]
+    fn prevented_tab_keeps_focus() {
+        let mut root = Node::new();
+        {
+            let mut children = root.edit_children();
+            let first = children.add();
+            first.set_focusable(true);
+            first.on_key_down(|_, _, event| event.prevent_default());
+            children.add().set_focusable(true);
+        }
+        root.children_mut()[0].request_focus();
+
+        let mut dispatcher = Dispatcher::new();
+        press_key(&mut dispatcher, &mut root, Key::Tab, false);
+        assert_eq!(root.focus_path(), Some(vec![0]));
+    }
+
+    #[test]
+    fn constraints_keep_max_above_min() {
+        let constraints = Constraints::new(-5.0, 10.0, 20.0, 10.0);
+        assert_eq!((constraints.min_width(), constraints.max_width()), (0.0, 10.0));
+        assert_eq!((constraints.min_height(), constraints.max_height()), (20.0, 20.0));
+    }
+
+    #[test]
+    fn constraints_deflate_and_constrain() {
+        let constraints = Constraints::tight(100.0, 50.0);
+        assert!(constraints.is_tight());
+        assert!(!constraints.loosen().is_tight());
+
+        let deflated = constraints.deflate(20.0, 60.0);
+        assert_eq!((deflated.min_width(), deflated.max_width()), (80.0, 80.0));
+        assert_eq!((deflated.min_height(), deflated.max_height()), (0.0, 0.0));
+
+        let loose = Constraints::loose(100.0, 50.0);
+        assert_eq!(loose.constrain(150.0, 20.0), (100.0, 20.0));
+        assert!(!Constraints::unbounded().has_bounded_width());
+    }
+
+    #[test]
+    fn keyed_children_keep_state_when_reordered() {
+        let mut root = Node::new();
+        {
+            let mut children = root.edit_children();
+            *children.add_keyed("a").state(|| 0) = 1;
+            *children.add_keyed("b").state(|| 0) = 2;
+        }
+        {
+            let mut children = root.edit_children();
+            assert_eq!(*children.add_keyed("b").state(|| 0), 2);
+            assert_eq!(*children.add_keyed("c").state(|| 0), 0);
+            assert_eq!(*children.add_keyed("a").state(|| 0), 1);
+        }
+        assert_eq!(root.children().len(), 3);
+
+        root.edit_children().add_keyed("a");
+        assert_eq!(root.children().len(), 1);
+    }
+
+    #[test]
+    fn unkeyed_children_skip_keyed_ones() {
+        let mut root = Node::new();
+        {
+            let mut children = root.edit_children();
+            children.add_keyed("x");
+            *children.add().state(|| 0) = 1;
+        }
+        {
+            let mut children = root.edit_children();
+            assert_eq!(*children.add().state(|| 0), 1);
+        }
+        assert_eq!(root.children().len(), 1);
+    }
+
+    #[test]
+    fn wrappers_forward_keys() {
+        let key = Some(hash_key("a"));
+        assert_eq!(Padding::new(4.0, Keyed::new("a", Empty)).key(), key);
+        assert_eq!(FocusScope::new(BackgroundColor::new(Color::rgba(0.0, 0.0, 0.0, 1.0), Keyed::new("a", Empty))).key(), key);
+    }
+
+    fn text_glyphs(node: &Node) -> (&[Glyph], &[f32]) {
+        match node.shape() {
+            Shape::Text { glyphs, advances, .. } => (&glyphs[..], &advances[..]),
+            _ => panic!("not a text node"),
+        }
+    }
+
+    #[test]
+    fn text_wraps_to_max_width() {
+        let font = Theme::default_font();
+        let line_height = edit::line_height(&font, 14.0);
+        let constraints = Constraints::loose(font.measure("the quick ", 14.0).0, std::f32::INFINITY);
+
+        let mut node = Node::new();
+        Text::new(font.clone(), 14.0, "the quick brown fox").apply(&mut node, constraints);
+        assert!(node.size().0 <= constraints.max_width());
+        assert!(node.size().1 >= 2.0 * line_height);
+
+        let mut node = Node::new();
+        Text::new(font.clone(), 14.0, "the quick brown fox").wrap(false).apply(&mut node, constraints);
+        assert_eq!(node.size().1, line_height);
+    }
+
+    #[test]
+    fn text_ellipsizes_last_line() {
+        let font = Theme::default_font();
+        let width = font.measure("hello", 14.0).0 + font.measure("\u{2026}", 14.0).0 + 0.5;
+
+        let text = Text::new(font.clone(), 14.0, "hello world");
+        assert_eq!(text.ellipsize(&font, 14.0, "hello world", width), "hello\u{2026}");
+
+        let mut node = Node::new();
+        text.max_lines(1).apply(&mut node, Constraints::loose(width, std::f32::INFINITY));
+        assert_eq!(node.size().1, edit::line_height(&font, 14.0));
+        assert_eq!(text_glyphs(&node).0.len(), "hello\u{2026}".chars().count());
+    }
+
+    #[test]
+    fn justified_lines_fill_max_width() {
+        let font = Theme::default_font();
+        let width = font.measure("aaa bbb ccc ", 14.0).0 + 1.0;
+
+        let mut node = Node::new();
+        Text::new(font.clone(), 14.0, "aaa bbb ccc ddd")
+            .align(TextAlign::Justify)
+            .apply(&mut node, Constraints::loose(width, std::f32::INFINITY));
+        assert_eq!(node.size().0, width);
+
+        // The last character of the first line ends at the right edge, while the last line,
+        // which ends the paragraph, isn't stretched.
+        let (glyphs, advances) = text_glyphs(&node);
+        assert!((glyphs[10].position.x + advances[10] - width).abs() < 0.5);
+        assert_eq!(glyphs[12].position.x, glyphs[0].position.x);
+    }
+
+    /// Builds a root with a child and a grandchild covering it, each recording the mouse down
+    /// events it receives in either phase. `stop` names a node whose regular handler stops
+    /// propagation.
+    fn nested(log: &Rc<RefCell<Vec<(&'static str, Phase)>>>, stop: &'static str) -> Node {
+        fn record(node: &mut Node, name: &'static str, log: &Rc<RefCell<Vec<(&'static str, Phase)>>>, stop: bool) {
+            node.set_size(100.0, 100.0);
+            node.on_mouse_down_capture({
+                let log = log.clone();
+                move |_, _, event| log.borrow_mut().push((name, event.phase()))
+            });
+            node.on_mouse_down({
+                let log = log.clone();
+                move |_, _, event| {
+                    log.borrow_mut().push((name, event.phase()));
+                    if stop {
+                        event.stop_propagation();
+                    }
+                }
+            });
+        }
+
+        let mut root = Node::new();
+        record(&mut root, "root", log, stop == "root");
+        {
+            let mut children = root.edit_children();
+            let child = children.add();
+            record(child, "child", log, stop == "child");
+            record(child.edit_children().add(), "grandchild", log, stop == "grandchild");
+        }
+        root
+    }
+
+    fn mouse_down(root: &mut Node) {
+        let input_state = InputState { mouse_x: 10.0, mouse_y: 10.0, ..InputState::default() };
+        Dispatcher::new().input(root, Input::MouseDown(MouseButton::Left), &input_state);
+    }
+
+    #[test]
+    fn events_capture_down_and_bubble_up() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        mouse_down(&mut nested(&log, ""));
+        assert_eq!(*log.borrow(), vec![
+            ("root", Phase::Capture),
+            ("child", Phase::Capture),
+            ("grandchild", Phase::Target),
+            ("grandchild", Phase::Target),
+            ("child", Phase::Bubble),
+            ("root", Phase::Bubble),
+        ]);
+    }
+
+    #[test]
+    fn stop_propagation_skips_remaining_nodes() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        mouse_down(&mut nested(&log, "child"));
+        assert_eq!(log.borrow().last(), Some(&("child", Phase::Bubble)));
+        assert_eq!(log.borrow().len(), 5);
+    }
+
+    /// Builds a root with two overlapping children, the second on top of the first.
+    fn overlapping() -> Node {
+        let mut root = Node::new();
+        root.set_size(100.0, 100.0);
+        {
+            let mut children = root.edit_children();
+            children.add().set_size(50.0, 50.0);
+            let top = children.add();
+            top.set_offset(20.0, 20.0);
+            top.set_size(50.0, 50.0);
+        }
+        root
+    }
+
+    #[test]
+    fn hover_follows_topmost_node() {
+        let mut root = overlapping();
+        let mut dispatcher = Dispatcher::new();
+        let input_state = InputState { mouse_x: 30.0, mouse_y: 30.0, ..InputState::default() };
+        dispatcher.input(&mut root, Input::MouseMove, &input_state);
+        assert!(root.hover());
+        assert!(!root.children()[0].hover());
+        assert!(root.children()[1].hover());
+
+        // Moving content under a stationary mouse updates the hover after the next layout.
+        root.children_mut()[1].set_offset(60.0, 60.0);
+        dispatcher.update_hover(&mut root, &input_state);
+        assert!(root.children()[0].hover());
+        assert!(!root.children()[1].hover());
+    }
+
+    #[test]
+    fn capture_ends_with_its_own_button() {
+        let moves = Rc::new(Cell::new(0));
+        let mut root = Node::new();
+        root.set_size(100.0, 100.0);
+        {
+            let mut children = root.edit_children();
+            let handle = children.add();
+            handle.set_size(50.0, 50.0);
+            handle.on_mouse_down(|button, _, event| {
+                if button == MouseButton::Left {
+                    event.capture_pointer();
+                }
+            });
+            handle.on_mouse_move({
+                let moves = moves.clone();
+                move |_, _| moves.set(moves.get() + 1)
+            });
+        }
+
+        let mut dispatcher = Dispatcher::new();
+        let inside = InputState { mouse_x: 10.0, mouse_y: 10.0, ..InputState::default() };
+        let outside = InputState { mouse_x: 500.0, mouse_y: 500.0, ..InputState::default() };
+        dispatcher.input(&mut root, Input::MouseDown(MouseButton::Left), &inside);
+        assert!(root.children()[0].dragging());
+        dispatcher.input(&mut root, Input::MouseMove, &outside);
+        assert_eq!(moves.get(), 1);
+
+        dispatcher.input(&mut root, Input::MouseDown(MouseButton::Right), &inside);
+        dispatcher.input(&mut root, Input::MouseUp(MouseButton::Right), &inside);
+        assert!(root.children()[0].dragging());
+
+        dispatcher.input(&mut root, Input::MouseUp(MouseButton::Left), &outside);
+        assert!(!root.children()[0].dragging());
+        dispatcher.input(&mut root, Input::MouseMove, &outside);
+        assert_eq!(moves.get(), 1);
+    }
+
+    fn click_recorder() -> (Node, Rc<RefCell<Vec<(MouseButton, u32)>>>) {
+        let clicks = Rc::new(RefCell::new(Vec::new()));
+        let mut root = Node::new();
+        root.set_size(100.0, 100.0);
+        root.on_click({
+            let clicks = clicks.clone();
+            move |button, count, _, _| clicks.borrow_mut().push((button, count))
+        });
+        (root, clicks)
+    }
+
+    fn click(dispatcher: &mut Dispatcher, root: &mut Node, x: f32, y: f32) {
+        let input_state = InputState { mouse_x: x, mouse_y: y, ..InputState::default() };
+        dispatcher.input(root, Input::MouseDown(MouseButton::Left), &input_state);
+        dispatcher.input(root, Input::MouseUp(MouseButton::Left), &input_state);
+    }
+
+    #[test]
+    fn repeated_presses_count_clicks() {
+        let (mut root, clicks) = click_recorder();
+        let mut dispatcher = Dispatcher::new();
+        for count in 1..=3 {
+            click(&mut dispatcher, &mut root, 10.0, 10.0);
+            assert!(clicks.borrow().last() == Some(&(MouseButton::Left, count)));
+        }
+
+        click(&mut dispatcher, &mut root, 50.0, 50.0);
+        assert!(clicks.borrow().last() == Some(&(MouseButton::Left, 1)));
+    }
+
+    #[test]
+    fn overlapping_presses_each_click() {
+        let (mut root, clicks) = click_recorder();
+        let mut dispatcher = Dispatcher::new();
+        let input_state = InputState { mouse_x: 10.0, mouse_y: 10.0, ..InputState::default() };
+        dispatcher.input(&mut root, Input::MouseDown(MouseButton::Left), &input_state);
+        dispatcher.input(&mut root, Input::MouseDown(MouseButton::Right), &input_state);
+        dispatcher.input(&mut root, Input::MouseUp(MouseButton::Left), &input_state);
+        dispatcher.input(&mut root, Input::MouseUp(MouseButton::Right), &input_state);
+
+        let buttons: Vec<MouseButton> = clicks.borrow().iter().map(|&(button, _)| button).collect();
+        assert!(buttons == vec![MouseButton::Left, MouseButton::Right]);
+    }
+
+    #[test]
+    fn leaving_the_window_clears_hover() {
+        let left = Rc::new(Cell::new(false));
+        let mut root = overlapping();
+        root.children_mut()[1].on_mouse_leave({
+            let left = left.clone();
+            move |_| left.set(true)
+        });
+
+        let mut dispatcher = Dispatcher::new();
+        let input_state = InputState { mouse_x: 30.0, mouse_y: 30.0, ..InputState::default() };
+        dispatcher.input(&mut root, Input::MouseMove, &input_state);
+        dispatcher.input(&mut root, Input::MouseLeave, &input_state);
+        assert!(!root.hover() && !root.children()[1].hover());
+        assert!(left.get());
+
+        // Nothing is hovered again until the mouse moves back into the window.
+        dispatcher.update_hover(&mut root, &input_state);
+        assert!(!root.hover());
+    }
 }
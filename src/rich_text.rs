@@ -0,0 +1,422 @@
+use std::ops::Range;
+use std::rc::Rc;
+
+use gouache::{Color, Font, Glyph, Vec2};
+
+use crate::edit::{advances, baseline, caret_offsets, line_height};
+use crate::*;
+
+/// A run of text drawn in a single style, used as part of a `RichText`.
+pub struct Span<T: AsRef<str>> {
+    text: T,
+    font: Option<Rc<Font<'static>>>,
+    size: Option<f32>,
+    color: Option<Color>,
+    underline: bool,
+    strikethrough: bool,
+    background: Option<Color>,
+}
+
+impl<T: AsRef<str>> Span<T> {
+    pub fn new(font: Rc<Font<'static>>, size: f32, text: T) -> Span<T> {
+        Span { font: Some(font), size: Some(size), ..Span::themed(text) }
+    }
+
+    /// Creates a span drawn in the font and text size of the current `Theme`.
+    pub fn themed(text: T) -> Span<T> {
+        Span {
+            text,
+            font: None,
+            size: None,
+            color: None,
+            underline: false,
+            strikethrough: false,
+            background: None,
+        }
+    }
+
+    /// Overrides the text color of the current `Theme`.
+    pub fn color(self, color: Color) -> Span<T> {
+        Span { color: Some(color), ..self }
+    }
+
+    pub fn underline(self, underline: bool) -> Span<T> {
+        Span { underline, ..self }
+    }
+
+    pub fn strikethrough(self, strikethrough: bool) -> Span<T> {
+        Span { strikethrough, ..self }
+    }
+
+    pub fn background(self, background: Color) -> Span<T> {
+        Span { background: Some(background), ..self }
+    }
+}
+
+/// A paragraph made of differently styled spans. The spans are wrapped together as one text,
+/// so a line may break inside a span and a word may continue across span boundaries, and all
+/// spans on a line share a common baseline.
+pub struct RichText<T: AsRef<str>> {
+    spans: Vec<Span<T>>,
+    align: TextAlign,
+    wrap: bool,
+}
+
+impl<T: AsRef<str>> RichText<T> {
+    pub fn new(spans: Vec<Span<T>>) -> RichText<T> {
+        RichText { spans, align: TextAlign::Left, wrap: true }
+    }
+
+    /// Sets the alignment of each line. `TextAlign::Justify` is treated as `TextAlign::Left`.
+    pub fn align(self, align: TextAlign) -> RichText<T> {
+        RichText { align, ..self }
+    }
+
+    pub fn wrap(self, wrap: bool) -> RichText<T> {
+        RichText { wrap, ..self }
+    }
+
+    fn layout(&self, faces: &[Face], constraints: Constraints) -> RichTextLayout {
+        let max_width = if self.wrap { constraints.max_width() } else { std::f32::INFINITY };
+
+        let mut lines = vec![Line::default()];
+        let mut word: Vec<Segment> = Vec::new();
+        let mut line_x = 0.0;
+
+        for (index, (span, face)) in self.spans.iter().zip(faces).enumerate() {
+            let text = span.text.as_ref();
+
+            for (range, newline) in segments(text) {
+                if !range.is_empty() {
+                    let segment_text = &text[range.clone()];
+                    let width = face.font.measure(segment_text, face.size).0;
+                    let fit_width = face.font.measure(segment_text.trim_end(), face.size).0;
+                    word.push(Segment { span: index, range, x: 0.0, width, fit_width, glyphs: Vec::new(), advances: Vec::new() });
+                }
+
+                let ends_word = newline || word.last().map_or(false, |segment| {
+                    text[segment.range.clone()].ends_with(char::is_whitespace)
+                });
+                if ends_word {
+                    line_x = place_word(&self.spans, faces, &mut lines, &mut word, line_x, max_width);
+                }
+                if newline {
+                    lines.push(Line::default());
+                    lines.last_mut().unwrap().include(face);
+                    line_x = 0.0;
+                }
+            }
+        }
+        place_word(&self.spans, faces, &mut lines, &mut word, line_x, max_width);
+
+        let natural_width = lines.iter().map(|line| line.width).fold(0.0, f32::max);
+        let box_width = natural_width.min(constraints.max_width()).max(constraints.min_width());
+
+        let mut y = 0.0;
+        for line in lines.iter_mut() {
+            let x = match self.align {
+                TextAlign::Left | TextAlign::Justify => 0.0,
+                TextAlign::Center => (box_width - line.width) / 2.0,
+                TextAlign::Right => box_width - line.width,
+            };
+            line.top = y;
+            for segment in line.segments.iter_mut() {
+                let face = &faces[segment.span];
+                let text = &self.spans[segment.span].text.as_ref()[segment.range.clone()];
+                let dy = y + line.ascent - baseline(&face.font, face.size);
+                segment.x += x;
+                segment.glyphs = face.font.layout(text, face.size);
+                segment.advances = advances(&segment.glyphs, segment.width);
+                for glyph in segment.glyphs.iter_mut() {
+                    glyph.position.x += segment.x;
+                    glyph.position.y += dy;
+                }
+            }
+            y += line.ascent + line.descent;
+        }
+
+        let baseline = lines.first().map_or(0.0, |line| line.ascent);
+        RichTextLayout { width: box_width, height: y, baseline, lines }
+    }
+}
+
+/// Splits `text` at each point where a line may break: after a run of whitespace and at each
+/// newline. Each segment is paired with whether it is followed by a newline, which is excluded
+/// from the segment.
+fn segments(text: &str) -> Vec<(Range<usize>, bool)> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    let mut prev_whitespace = false;
+    for (index, c) in text.char_indices() {
+        if c == '\n' {
+            segments.push((start..index, true));
+            start = index + 1;
+            prev_whitespace = false;
+        } else {
+            if prev_whitespace && !c.is_whitespace() && index > start {
+                segments.push((start..index, false));
+                start = index;
+            }
+            prev_whitespace = c.is_whitespace();
+        }
+    }
+    if start < text.len() {
+        segments.push((start..text.len(), false));
+    }
+    segments
+}
+
+/// Moves the pending segments of a word onto the current line, starting a new line first if
+/// the word doesn't fit. A word wider than a whole line is broken between characters.
+fn place_word<T: AsRef<str>>(
+    spans: &[Span<T>],
+    faces: &[Face],
+    lines: &mut Vec<Line>,
+    word: &mut Vec<Segment>,
+    line_x: f32,
+    max_width: f32,
+) -> f32 {
+    if word.is_empty() {
+        return line_x;
+    }
+
+    let (last, rest) = word.split_last().unwrap();
+    let fit_width: f32 = rest.iter().map(|segment| segment.width).sum::<f32>() + last.fit_width;
+
+    let mut x = line_x;
+    if x > 0.0 && x + fit_width > max_width {
+        lines.push(Line::default());
+        x = 0.0;
+    }
+
+    let breaking = fit_width > max_width;
+    for mut segment in word.drain(..) {
+        while breaking && x + segment.fit_width > max_width {
+            let face = &faces[segment.span];
+            let text = &spans[segment.span].text.as_ref()[segment.range.clone()];
+            let offsets = caret_offsets(&face.font, face.size, text);
+
+            // Take as many characters as fit, but at least one on an empty line.
+            let fitting = offsets.iter().skip(1).take_while(|&&(_, offset)| x + offset <= max_width).last();
+            let (end, head_width) = match fitting {
+                Some(&(end, head_width)) => (end, head_width),
+                None if x == 0.0 => offsets.get(1).cloned().unwrap_or((text.len(), segment.width)),
+                None => (0, 0.0),
+            };
+            if end >= text.len() {
+                break;
+            }
+
+            if end > 0 {
+                let head = Segment {
+                    span: segment.span,
+                    range: segment.range.start..segment.range.start + end,
+                    x: 0.0,
+                    width: head_width,
+                    fit_width: head_width,
+                    glyphs: Vec::new(),
+                    advances: Vec::new(),
+                };
+                push_segment(lines.last_mut().unwrap(), face, head, x);
+                segment.range.start += end;
+                segment.width -= head_width;
+                segment.fit_width -= head_width;
+            }
+            lines.push(Line::default());
+            x = 0.0;
+        }
+
+        let face = &faces[segment.span];
+        x = push_segment(lines.last_mut().unwrap(), face, segment, x);
+    }
+    x
+}
+
+/// Adds `segment` to `line` at `x`, returning the position after it.
+fn push_segment(line: &mut Line, face: &Face, mut segment: Segment, x: f32) -> f32 {
+    line.include(face);
+    segment.x = x;
+    line.width = line.width.max(x + segment.fit_width);
+    let end = x + segment.width;
+    line.segments.push(segment);
+    end
+}
+
+struct Segment {
+    span: usize,
+    range: Range<usize>,
+    x: f32,
+    width: f32,
+    fit_width: f32,
+    /// The positioned glyphs, which are moved into the segment's text node once laid out.
+    glyphs: Vec<Glyph>,
+    advances: Vec<f32>,
+}
+
+#[derive(Default)]
+struct Line {
+    segments: Vec<Segment>,
+    top: f32,
+    width: f32,
+    ascent: f32,
+    descent: f32,
+}
+
+impl Line {
+    fn include(&mut self, face: &Face) {
+        let ascent = baseline(&face.font, face.size);
+        self.ascent = self.ascent.max(ascent);
+        self.descent = self.descent.max(line_height(&face.font, face.size) - ascent);
+    }
+}
+
+struct RichTextLayout {
+    width: f32,
+    height: f32,
+    baseline: f32,
+    lines: Vec<Line>,
+}
+
+/// The font and size of a span, with those of the current `Theme` filled in.
+#[derive(Clone)]
+struct Face {
+    font: Rc<Font<'static>>,
+    size: f32,
+}
+
+/// The layout-affecting parameters of a span, used to decide whether a cached layout is stale.
+struct SpanKey {
+    face: Face,
+    text: String,
+}
+
+struct RichTextState {
+    spans: Vec<SpanKey>,
+    align: TextAlign,
+    wrap: bool,
+    constraints: Constraints,
+    layout: RichTextLayout,
+}
+
+impl<T: AsRef<str>> Elem for RichText<T> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
+
+        let theme = Theme::current();
+        let faces: Vec<Face> = self.spans.iter().map(|span| Face {
+            font: span.font.clone().unwrap_or_else(|| theme.font.clone()),
+            size: span.size.unwrap_or(theme.text_size),
+        }).collect();
+
+        let stale = match node.state(|| None::<RichTextState>) {
+            Some(state) => {
+                state.align != self.align || state.wrap != self.wrap ||
+                state.constraints != constraints || state.spans.len() != self.spans.len() ||
+                state.spans.iter().zip(self.spans.iter().zip(faces.iter())).any(|(key, (span, face))| {
+                    !Rc::ptr_eq(&key.face.font, &face.font) || key.face.size != face.size ||
+                    key.text != span.text.as_ref()
+                })
+            }
+            None => true,
+        };
+        if stale {
+            let layout = self.layout(&faces, constraints);
+            *node.state(|| None::<RichTextState>) = Some(RichTextState {
+                spans: self.spans.iter().zip(faces.iter()).map(|(span, face)| SpanKey {
+                    face: face.clone(),
+                    text: span.text.as_ref().to_string(),
+                }).collect(),
+                align: self.align,
+                wrap: self.wrap,
+                constraints,
+                layout,
+            });
+        }
+
+        let text_color = theme.text_color;
+        let mut state = node.state(|| None::<RichTextState>).take().unwrap();
+        let (width, height) = constraints.constrain(state.layout.width, state.layout.height);
+
+        {
+            // Backgrounds, text and decorations each get a layer, so that the text nodes stay
+            // in place when backgrounds or decorations are added or removed.
+            let mut children = node.edit_children();
+
+            let backgrounds = children.add();
+            backgrounds.set_size(width, height);
+            let mut backgrounds = backgrounds.edit_children();
+            for line in state.layout.lines.iter() {
+                for segment in line.segments.iter() {
+                    if let Some(background) = self.spans[segment.span].background {
+                        backgrounds.add().set_shape(Shape::Rect {
+                            position: Vec2::new(segment.x, line.top),
+                            dimensions: Vec2::new(segment.width, line.ascent + line.descent),
+                            color: background,
+                        });
+                    }
+                }
+            }
+            drop(backgrounds);
+
+            // The glyphs are only handed to the text nodes when they have been laid out anew;
+            // otherwise just the colors are updated.
+            let texts = children.add();
+            texts.set_size(width, height);
+            if stale {
+                let mut texts = texts.edit_children();
+                for line in state.layout.lines.iter_mut() {
+                    for segment in line.segments.iter_mut() {
+                        let face = &faces[segment.span];
+                        texts.add().set_shape(Shape::Text {
+                            position: Vec2::new(0.0, 0.0),
+                            font: face.font.clone(),
+                            size: face.size,
+                            glyphs: std::mem::replace(&mut segment.glyphs, Vec::new()),
+                            advances: std::mem::replace(&mut segment.advances, Vec::new()),
+                            color: self.spans[segment.span].color.unwrap_or(text_color),
+                        });
+                    }
+                }
+            } else {
+                let segments = state.layout.lines.iter().flat_map(|line| line.segments.iter());
+                for (text, segment) in texts.children_mut().iter_mut().zip(segments) {
+                    if let Shape::Text { color: ref mut shape_color, .. } = text.shape {
+                        *shape_color = self.spans[segment.span].color.unwrap_or(text_color);
+                    }
+                }
+            }
+
+            let decorations = children.add();
+            decorations.set_size(width, height);
+            let mut decorations = decorations.edit_children();
+            for line in state.layout.lines.iter() {
+                let baseline_y = line.top + line.ascent;
+                for (i, segment) in line.segments.iter().enumerate() {
+                    let span = &self.spans[segment.span];
+                    let face = &faces[segment.span];
+                    let thickness = (face.size / 14.0).max(1.0);
+                    // Decorations run through the spaces between words, but not past the end
+                    // of the line.
+                    let decoration_width = if i + 1 == line.segments.len() { segment.fit_width } else { segment.width };
+                    let mut decorate = |y: f32| {
+                        decorations.add().set_shape(Shape::Rect {
+                            position: Vec2::new(segment.x, y),
+                            dimensions: Vec2::new(decoration_width, thickness),
+                            color: span.color.unwrap_or(text_color),
+                        });
+                    };
+                    if span.underline {
+                        decorate(baseline_y + thickness);
+                    }
+                    if span.strikethrough {
+                        decorate(baseline_y - 0.3 * baseline(&face.font, face.size));
+                    }
+                }
+            }
+        }
+
+        node.set_size(width, height);
+        node.set_baseline(Some(state.layout.baseline));
+        *node.state(|| None::<RichTextState>) = Some(state);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::*;
+use crate::flex::align_offset;
+
+/// How a child of a `Stack` is positioned within it.
+#[derive(Copy, Clone)]
+pub struct StackPlacement {
+    pub align_x: Align,
+    pub align_y: Align,
+    pub offset_x: f32,
+    pub offset_y: f32,
+}
+
+impl Default for StackPlacement {
+    fn default() -> StackPlacement {
+        StackPlacement {
+            align_x: Align::Start,
+            align_y: Align::Start,
+            offset_x: 0.0,
+            offset_y: 0.0,
+        }
+    }
+}
+
+pub struct Layer<C: Elem> {
+    placement: StackPlacement,
+    child: C,
+}
+
+impl<C: Elem> Layer<C> {
+    pub fn new(child: C) -> Layer<C> {
+        Layer { placement: StackPlacement::default(), child }
+    }
+
+    pub fn align(mut self, align_x: Align, align_y: Align) -> Layer<C> {
+        self.placement.align_x = align_x;
+        self.placement.align_y = align_y;
+        self
+    }
+
+    pub fn offset(mut self, offset_x: f32, offset_y: f32) -> Layer<C> {
+        self.placement.offset_x = offset_x;
+        self.placement.offset_y = offset_y;
+        self
+    }
+}
+
+impl<C: Elem> Elem for Layer<C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        self.child.apply(node, constraints);
+    }
+
+    fn flex(&self) -> FlexParams {
+        self.child.flex()
+    }
+
+    fn grid_placement(&self) -> GridPlacement {
+        self.child.grid_placement()
+    }
+
+    fn stack_placement(&self) -> StackPlacement {
+        self.placement
+    }
+
+    fn key(&self) -> Option<u64> {
+        self.child.key()
+    }
+}
+
+/// Layers its children on top of each other, drawing them in order. The stack is as large as
+/// its largest child, and the topmost child under the mouse receives mouse input.
+pub struct Stack<C: ElemList> {
+    children: C,
+}
+
+impl<C: ElemList> Stack<C> {
+    pub fn new(children: C) -> Stack<C> {
+        Stack { children }
+    }
+}
+
+impl<C: ElemList> Elem for Stack<C> {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!().of::<Self>());
+
+        let mut placements = Vec::new();
+        let mut width: f32 = 0.0;
+        let mut height: f32 = 0.0;
+        {
+            let mut children = node.edit_children();
+            self.children.with_items(&mut |items| {
+                for item in items.iter_mut() {
+                    let placement = item.stack_placement();
+                    let mut child_constraints = constraints.loosen();
+                    if placement.align_x == Align::Stretch && constraints.has_bounded_width() {
+                        child_constraints = child_constraints.with_width(constraints.max_width(), constraints.max_width());
+                    }
+                    if placement.align_y == Align::Stretch && constraints.has_bounded_height() {
+                        child_constraints = child_constraints.with_height(constraints.max_height(), constraints.max_height());
+                    }
+
+                    let child = children.add_with_key(item.key());
+                    item.apply(child, child_constraints);
+                    let (child_width, child_height) = child.size();
+                    width = width.max(child_width);
+                    height = height.max(child_height);
+                    placements.push(placement);
+                }
+            });
+        }
+
+        let (width, height) = constraints.constrain(width, height);
+        for (child, placement) in node.children_mut().iter_mut().zip(placements) {
+            let (child_width, child_height) = child.size();
+            child.set_offset(
+                align_offset(placement.align_x, width, child_width) + placement.offset_x,
+                align_offset(placement.align_y, height, child_height) + placement.offset_y,
+            );
+        }
+
+        node.set_size(width, height);
+    }
+}
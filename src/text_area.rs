@@ -0,0 +1,394 @@
+use std::ops::Range;
+use std::rc::Rc;
+
+use gouache::{Font, Vec2};
+
+use crate::edit::{EditBuffer, accepts_char, advances, baseline, caret_offsets, caret_x, hit_test, line_height, normalize_newlines, wrap_lines};
+use crate::input::{Key, Modifiers, MouseButton};
+use crate::*;
+
+const DEFAULT_PAGE_LINES: usize = 10;
+
+/// A multi-line text field. Tab inserts a tab character, so focus leaves the field with
+/// Shift+Tab (to the previous node) or Ctrl+Tab (to the next one).
+pub struct TextArea {
+    font: Option<Rc<Font<'static>>>,
+    size: Option<f32>,
+    text: Option<String>,
+    on_change: Option<Sender<String>>,
+    style: Option<TextInputStyle>,
+}
+
+impl TextArea {
+    pub fn new(font: Rc<Font<'static>>, size: f32) -> TextArea {
+        TextArea { font: Some(font), size: Some(size), ..TextArea::themed() }
+    }
+
+    /// Creates a text area using the font and text size of the current `Theme`.
+    pub fn themed() -> TextArea {
+        TextArea { font: None, size: None, text: None, on_change: None, style: None }
+    }
+
+    /// Sets the text being edited. As with `TextInput::text`, the text is replaced whenever
+    /// this value differs from the one given in the previous frame.
+    pub fn text<S: Into<String>>(self, text: S) -> TextArea {
+        TextArea { text: Some(text.into()), ..self }
+    }
+
+    pub fn on_change(self, on_change: Sender<String>) -> TextArea {
+        TextArea { on_change: Some(on_change), ..self }
+    }
+
+    /// Overrides the text input style of the current `Theme`.
+    pub fn style(self, style: TextInputStyle) -> TextArea {
+        TextArea { style: Some(style), ..self }
+    }
+}
+
+enum TextAreaEvent {
+    MouseDown(f32, f32, Modifiers),
+    MouseMove(f32, f32),
+    MouseUp,
+    KeyDown(Key, Modifiers),
+    Char(char, Modifiers),
+    Cut,
+    Paste(String),
+}
+
+struct TextAreaState {
+    buffer: EditBuffer,
+    /// The text most recently passed to `TextArea::text`.
+    value: Option<String>,
+    goal_x: Option<f32>,
+    selecting: bool,
+    rx: Receiver<TextAreaEvent>,
+}
+
+struct Layout {
+    lines: Vec<Range<usize>>,
+    offsets: Vec<Vec<(usize, f32)>>,
+    line_height: f32,
+}
+
+impl Layout {
+    fn new(font: &Font<'static>, size: f32, text: &str, width: f32) -> Layout {
+        let lines = wrap_lines(font, size, text, width);
+        let offsets = lines.iter().map(|line| {
+            caret_offsets(font, size, &text[line.clone()]).into_iter()
+                .map(|(index, x)| (line.start + index, x))
+                .collect()
+        }).collect();
+        Layout { lines, offsets, line_height: line_height(font, size) }
+    }
+
+    fn line_of(&self, index: usize) -> usize {
+        self.lines.iter().rposition(|line| line.start <= index).unwrap_or(0)
+    }
+
+    fn caret_position(&self, index: usize) -> (f32, f32) {
+        let line = self.line_of(index);
+        (caret_x(&self.offsets[line], index), line as f32 * self.line_height)
+    }
+
+    /// Finds the caret position on `line` closest to `x`. On a soft-wrapped line, the position
+    /// after the last character belongs to the following line, so it is excluded.
+    fn hit_test_line(&self, line: usize, x: f32) -> usize {
+        let offsets = &self.offsets[line];
+        let soft_wrapped = self.lines.get(line + 1).map_or(false, |next| next.start == self.lines[line].end);
+        if soft_wrapped && offsets.len() > 1 {
+            hit_test(&offsets[..offsets.len() - 1], x)
+        } else {
+            hit_test(offsets, x)
+        }
+    }
+
+    fn hit_test(&self, x: f32, y: f32) -> usize {
+        let line = ((y / self.line_height).max(0.0) as usize).min(self.lines.len() - 1);
+        self.hit_test_line(line, x)
+    }
+}
+
+/// Whether the text area acts on `key`, in which case the key isn't passed on to its ancestors.
+fn handles_key(key: Key, modifiers: Modifiers) -> bool {
+    match key {
+        Key::Backspace | Key::Delete | Key::Enter | Key::NumpadEnter |
+        Key::Left | Key::Right | Key::Up | Key::Down |
+        Key::PageUp | Key::PageDown | Key::Home | Key::End => true,
+        Key::Tab => !modifiers.ctrl && !modifiers.shift,
+        Key::A => modifiers.ctrl,
+        _ => false,
+    }
+}
+
+impl Elem for TextArea {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!());
+        node.set_focusable(true);
+
+        let theme = Theme::current();
+        let style = self.style.clone().unwrap_or_else(|| theme.text_input.clone());
+        let font = self.font.clone().unwrap_or_else(|| theme.font.clone());
+        let size = self.size.unwrap_or(theme.text_size);
+        let padding = style.padding;
+
+        let (x, y) = node.position();
+        let focused = node.focused();
+        let wrap_width = (constraints.max_width() - 2.0 * padding).max(0.0);
+        let line_height = line_height(&font, size);
+        let page_lines = if constraints.has_bounded_height() {
+            ((constraints.max_height() / line_height) as usize).max(1)
+        } else {
+            DEFAULT_PAGE_LINES
+        };
+
+        let state = node.state(|| TextAreaState {
+            buffer: EditBuffer::new(String::new()),
+            value: None,
+            goal_x: None,
+            selecting: false,
+            rx: Receiver::new(),
+        });
+
+        if let Some(text) = self.text {
+            if state.value.as_ref() != Some(&text) {
+                state.buffer.set_text(text.clone());
+                state.value = Some(text);
+            }
+        }
+
+        let mut changed = false;
+        let mut moved = false;
+        for event in state.rx.poll() {
+            let layout = Layout::new(&font, size, state.buffer.text(), wrap_width);
+            let mut goal_x = None;
+            match event {
+                TextAreaEvent::MouseDown(mouse_x, mouse_y, modifiers) => {
+                    let index = layout.hit_test(mouse_x - x - padding, mouse_y - y - padding);
+                    state.buffer.set_cursor(index, modifiers.shift);
+                    state.selecting = true;
+                }
+                TextAreaEvent::MouseMove(mouse_x, mouse_y) => {
+                    if state.selecting {
+                        let index = layout.hit_test(mouse_x - x - padding, mouse_y - y - padding);
+                        state.buffer.set_cursor(index, true);
+                    }
+                }
+                TextAreaEvent::MouseUp => {
+                    state.selecting = false;
+                }
+                TextAreaEvent::KeyDown(key, modifiers) => {
+                    match key {
+                        Key::Backspace => { changed |= state.buffer.delete_backward(); }
+                        Key::Delete => { changed |= state.buffer.delete_forward(); }
+                        Key::Enter | Key::NumpadEnter => {
+                            state.buffer.insert("\n");
+                            changed = true;
+                        }
+                        Key::Tab if !modifiers.ctrl && !modifiers.shift => {
+                            state.buffer.insert("\t");
+                            changed = true;
+                        }
+                        Key::Left if modifiers.ctrl => { state.buffer.move_word_left(modifiers.shift); }
+                        Key::Right if modifiers.ctrl => { state.buffer.move_word_right(modifiers.shift); }
+                        Key::Left => { state.buffer.move_left(modifiers.shift); }
+                        Key::Right => { state.buffer.move_right(modifiers.shift); }
+                        Key::Up | Key::Down | Key::PageUp | Key::PageDown => {
+                            let cursor = state.buffer.cursor();
+                            let line = layout.line_of(cursor);
+                            let target_x = state.goal_x.unwrap_or_else(|| layout.caret_position(cursor).0);
+                            let last = layout.lines.len() - 1;
+                            let target = match key {
+                                Key::Up => line.saturating_sub(1),
+                                Key::Down => (line + 1).min(last),
+                                Key::PageUp => line.saturating_sub(page_lines),
+                                _ => (line + page_lines).min(last),
+                            };
+                            let index = if target == line && (key == Key::Up || key == Key::PageUp) {
+                                0
+                            } else if target == line {
+                                state.buffer.text().len()
+                            } else {
+                                layout.hit_test_line(target, target_x)
+                            };
+                            state.buffer.set_cursor(index, modifiers.shift);
+                            goal_x = Some(target_x);
+                        }
+                        Key::Home if modifiers.ctrl => { state.buffer.set_cursor(0, modifiers.shift); }
+                        Key::End if modifiers.ctrl => {
+                            let end = state.buffer.text().len();
+                            state.buffer.set_cursor(end, modifiers.shift);
+                        }
+                        Key::Home => {
+                            let line = layout.line_of(state.buffer.cursor());
+                            state.buffer.set_cursor(layout.lines[line].start, modifiers.shift);
+                        }
+                        Key::End => {
+                            let line = layout.line_of(state.buffer.cursor());
+                            let index = layout.hit_test_line(line, std::f32::INFINITY);
+                            state.buffer.set_cursor(index, modifiers.shift);
+                        }
+                        Key::A if modifiers.ctrl => { state.buffer.select_all(); }
+                        _ => {}
+                    }
+                    moved = true;
+                }
+                TextAreaEvent::Char(c, modifiers) => {
+                    if accepts_char(c, modifiers) {
+                        let mut bytes = [0; 4];
+                        state.buffer.insert(c.encode_utf8(&mut bytes));
+                        changed = true;
+                        moved = true;
+                    }
+                }
+                TextAreaEvent::Cut => {
+                    changed |= state.buffer.delete_selection();
+                }
+                TextAreaEvent::Paste(text) => {
+                    state.buffer.insert(&normalize_newlines(&text));
+                    changed = true;
+                    moved = true;
+                }
+            }
+            state.goal_x = goal_x;
+        }
+
+        if changed {
+            if let Some(ref on_change) = self.on_change {
+                on_change.send(state.buffer.text().to_string());
+            }
+        }
+
+        let tx = state.rx.sender();
+        let text = state.buffer.text().to_string();
+        let selected_text = if focused && state.buffer.has_selection() {
+            Some(state.buffer.selected_text().to_string())
+        } else {
+            None
+        };
+        let cursor = state.buffer.cursor();
+        let selection = state.buffer.selection();
+
+        node.on_mouse_down({
+            let tx = tx.clone();
+            move |button, input_state, event| {
+                if button == MouseButton::Left {
+                    tx.send(TextAreaEvent::MouseDown(input_state.mouse_x, input_state.mouse_y, input_state.modifiers));
+                    event.capture_pointer();
+                }
+            }
+        });
+        node.on_mouse_move({
+            let tx = tx.clone();
+            move |input_state, event| {
+                tx.send(TextAreaEvent::MouseMove(input_state.mouse_x, input_state.mouse_y));
+            }
+        });
+        node.on_mouse_up({
+            let tx = tx.clone();
+            move |button, input_state, event| {
+                if button == MouseButton::Left {
+                    tx.send(TextAreaEvent::MouseUp);
+                }
+            }
+        });
+        node.on_key_down({
+            let tx = tx.clone();
+            move |key, input_state, event| {
+                let modifiers = input_state.modifiers;
+                if key == Key::Tab && !modifiers.ctrl && !modifiers.shift {
+                    // Tab inserts a tab character instead of moving focus. The tab is inserted
+                    // in response to the key event rather than the char event, so that it is
+                    // inserted exactly when focus traversal is prevented.
+                    event.prevent_default();
+                }
+                if handles_key(key, modifiers) {
+                    event.stop_propagation();
+                }
+                tx.send(TextAreaEvent::KeyDown(key, modifiers));
+            }
+        });
+        node.on_char({
+            let tx = tx.clone();
+            move |c, input_state, event| {
+                if accepts_char(c, input_state.modifiers) {
+                    event.stop_propagation();
+                }
+                tx.send(TextAreaEvent::Char(c, input_state.modifiers));
+            }
+        });
+        node.on_cut({
+            let tx = tx.clone();
+            move |input_state| {
+                tx.send(TextAreaEvent::Cut);
+            }
+        });
+        node.on_paste(move |text, input_state| {
+            tx.send(TextAreaEvent::Paste(text.to_string()));
+        });
+        node.set_selected_text(selected_text);
+
+        let layout = Layout::new(&font, size, &text, wrap_width);
+        let width = if constraints.has_bounded_width() {
+            constraints.max_width()
+        } else {
+            layout.offsets.iter().map(|offsets| offsets.last().unwrap().1).fold(0.0, f32::max) + 2.0 * padding
+        };
+        let height = layout.lines.len() as f32 * line_height + 2.0 * padding;
+        let (width, height) = constraints.constrain(width, height);
+
+        let background = if focused { style.focused_background } else { style.background };
+        node.set_shape(rounded_rect(Vec2::new(0.0, 0.0), Vec2::new(width, height), style.corner_radius, background));
+
+        let mut children = node.edit_children();
+
+        for (i, line) in layout.lines.iter().enumerate() {
+            let line_y = padding + i as f32 * line_height;
+
+            let highlight = children.add();
+            let start = selection.start.max(line.start);
+            let end = selection.end.min(line.end);
+            if focused && start < end {
+                let start_x = caret_x(&layout.offsets[i], start);
+                let end_x = caret_x(&layout.offsets[i], end);
+                highlight.set_shape(Shape::Rect {
+                    position: Vec2::new(padding + start_x, line_y),
+                    dimensions: Vec2::new(end_x - start_x, line_height),
+                    color: style.selection_color,
+                });
+            } else {
+                highlight.set_shape(Shape::Empty);
+            }
+
+            let label = children.add();
+            let glyphs = font.layout(&text[line.clone()], size);
+            label.set_shape(Shape::Text {
+                position: Vec2::new(padding, line_y),
+                font: font.clone(),
+                size,
+                advances: advances(&glyphs, caret_x(&layout.offsets[i], line.end)),
+                glyphs,
+                color: style.text_color,
+            });
+        }
+
+        let (cursor_x, cursor_y) = layout.caret_position(cursor);
+        let caret = children.add();
+        if focused {
+            caret.set_shape(Shape::Rect {
+                position: Vec2::new(padding + cursor_x, padding + cursor_y),
+                dimensions: Vec2::new(1.0, line_height),
+                color: style.caret_color,
+            });
+        } else {
+            caret.set_shape(Shape::Empty);
+        }
+
+        drop(children);
+        node.set_size(width, height);
+        node.set_baseline(Some(padding + baseline(&font, size)));
+
+        if focused && moved {
+            node.scroll_into_view(padding + cursor_x, cursor_y, 1.0, line_height + 2.0 * padding);
+        }
+    }
+}
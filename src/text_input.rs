@@ -0,0 +1,295 @@
+use std::rc::Rc;
+
+use gouache::{Font, Vec2};
+
+use crate::edit::{EditBuffer, accepts_char, advances, baseline, caret_offsets, caret_x, hit_test, line_height, normalize_newlines};
+use crate::input::{Key, Modifiers, MouseButton};
+use crate::*;
+
+pub struct TextInput {
+    font: Option<Rc<Font<'static>>>,
+    size: Option<f32>,
+    width: f32,
+    text: Option<String>,
+    on_change: Option<Sender<String>>,
+    style: Option<TextInputStyle>,
+}
+
+impl TextInput {
+    pub fn new(font: Rc<Font<'static>>, size: f32) -> TextInput {
+        TextInput { font: Some(font), size: Some(size), ..TextInput::themed() }
+    }
+
+    /// Creates a text input using the font and text size of the current `Theme`.
+    pub fn themed() -> TextInput {
+        TextInput { font: None, size: None, width: 200.0, text: None, on_change: None, style: None }
+    }
+
+    pub fn width(self, width: f32) -> TextInput {
+        TextInput { width, ..self }
+    }
+
+    /// Sets the text being edited. The input's text is replaced whenever this value differs
+    /// from the one given in the previous frame, so passing the value received from
+    /// `on_change` back in keeps the input in sync with application state, while edits made
+    /// in the meantime are kept.
+    pub fn text<S: Into<String>>(self, text: S) -> TextInput {
+        TextInput { text: Some(text.into()), ..self }
+    }
+
+    pub fn on_change(self, on_change: Sender<String>) -> TextInput {
+        TextInput { on_change: Some(on_change), ..self }
+    }
+
+    /// Overrides the text input style of the current `Theme`.
+    pub fn style(self, style: TextInputStyle) -> TextInput {
+        TextInput { style: Some(style), ..self }
+    }
+}
+
+enum TextInputEvent {
+    MouseDown(f32, Modifiers),
+    MouseMove(f32),
+    MouseUp,
+    KeyDown(Key, Modifiers),
+    Char(char, Modifiers),
+    Cut,
+    Paste(String),
+}
+
+struct TextInputState {
+    buffer: EditBuffer,
+    /// The text most recently passed to `TextInput::text`.
+    value: Option<String>,
+    /// How far the text is scrolled horizontally to keep the caret in view.
+    scroll: f32,
+    selecting: bool,
+    rx: Receiver<TextInputEvent>,
+}
+
+/// Whether the input acts on `key`, in which case the key isn't passed on to its ancestors.
+fn handles_key(key: Key, modifiers: Modifiers) -> bool {
+    match key {
+        Key::Backspace | Key::Delete | Key::Left | Key::Right | Key::Home | Key::End => true,
+        Key::A => modifiers.ctrl,
+        _ => false,
+    }
+}
+
+impl Elem for TextInput {
+    fn apply(self, node: &mut Node, constraints: Constraints) {
+        node.tag(id!());
+        node.set_focusable(true);
+
+        let theme = Theme::current();
+        let style = self.style.clone().unwrap_or_else(|| theme.text_input.clone());
+        let font = self.font.clone().unwrap_or_else(|| theme.font.clone());
+        let size = self.size.unwrap_or(theme.text_size);
+        let padding = style.padding;
+
+        let (x, _) = node.position();
+        let focused = node.focused();
+
+        let state = node.state(|| TextInputState {
+            buffer: EditBuffer::new(String::new()),
+            value: None,
+            scroll: 0.0,
+            selecting: false,
+            rx: Receiver::new(),
+        });
+
+        if let Some(text) = self.text {
+            if state.value.as_ref() != Some(&text) {
+                state.buffer.set_text(text.clone());
+                state.value = Some(text);
+            }
+        }
+
+        let mut changed = false;
+        for event in state.rx.poll() {
+            match event {
+                TextInputEvent::MouseDown(mouse_x, modifiers) => {
+                    let offsets = caret_offsets(&font, size, state.buffer.text());
+                    let index = hit_test(&offsets, mouse_x - x - padding + state.scroll);
+                    state.buffer.set_cursor(index, modifiers.shift);
+                    state.selecting = true;
+                }
+                TextInputEvent::MouseMove(mouse_x) => {
+                    if state.selecting {
+                        let offsets = caret_offsets(&font, size, state.buffer.text());
+                        let index = hit_test(&offsets, mouse_x - x - padding + state.scroll);
+                        state.buffer.set_cursor(index, true);
+                    }
+                }
+                TextInputEvent::MouseUp => {
+                    state.selecting = false;
+                }
+                TextInputEvent::KeyDown(key, modifiers) => {
+                    match key {
+                        Key::Backspace => { changed |= state.buffer.delete_backward(); }
+                        Key::Delete => { changed |= state.buffer.delete_forward(); }
+                        Key::Left if modifiers.ctrl => { state.buffer.move_word_left(modifiers.shift); }
+                        Key::Right if modifiers.ctrl => { state.buffer.move_word_right(modifiers.shift); }
+                        Key::Left => { state.buffer.move_left(modifiers.shift); }
+                        Key::Right => { state.buffer.move_right(modifiers.shift); }
+                        Key::Home => { state.buffer.set_cursor(0, modifiers.shift); }
+                        Key::End => {
+                            let end = state.buffer.text().len();
+                            state.buffer.set_cursor(end, modifiers.shift);
+                        }
+                        Key::A if modifiers.ctrl => { state.buffer.select_all(); }
+                        _ => {}
+                    }
+                }
+                TextInputEvent::Char(c, modifiers) => {
+                    if accepts_char(c, modifiers) {
+                        let mut bytes = [0; 4];
+                        state.buffer.insert(c.encode_utf8(&mut bytes));
+                        changed = true;
+                    }
+                }
+                TextInputEvent::Cut => {
+                    changed |= state.buffer.delete_selection();
+                }
+                TextInputEvent::Paste(text) => {
+                    state.buffer.insert(&normalize_newlines(&text).replace('\n', " "));
+                    changed = true;
+                }
+            }
+        }
+
+        if changed {
+            if let Some(ref on_change) = self.on_change {
+                on_change.send(state.buffer.text().to_string());
+            }
+        }
+
+        let tx = state.rx.sender();
+        let text = state.buffer.text().to_string();
+        let selected_text = if focused && state.buffer.has_selection() {
+            Some(state.buffer.selected_text().to_string())
+        } else {
+            None
+        };
+        let cursor = state.buffer.cursor();
+        let selection = state.buffer.selection();
+
+        let offsets = caret_offsets(&font, size, &text);
+        let text_height = line_height(&font, size);
+        let (width, height) = constraints.constrain(self.width, text_height + 2.0 * padding);
+        let view_width = (width - 2.0 * padding).max(0.0);
+
+        // Scroll just far enough to keep the caret, which is one pixel wide, in view.
+        let cursor_x = caret_x(&offsets, cursor);
+        let text_width = offsets.last().map_or(0.0, |&(_, x)| x);
+        if cursor_x < state.scroll {
+            state.scroll = cursor_x;
+        } else if cursor_x + 1.0 > state.scroll + view_width {
+            state.scroll = cursor_x + 1.0 - view_width;
+        }
+        state.scroll = state.scroll.min(text_width + 1.0 - view_width).max(0.0);
+        let scroll = state.scroll;
+
+        node.on_mouse_down({
+            let tx = tx.clone();
+            move |button, input_state, event| {
+                if button == MouseButton::Left {
+                    tx.send(TextInputEvent::MouseDown(input_state.mouse_x, input_state.modifiers));
+                    event.capture_pointer();
+                }
+            }
+        });
+        node.on_mouse_move({
+            let tx = tx.clone();
+            move |input_state, event| {
+                tx.send(TextInputEvent::MouseMove(input_state.mouse_x));
+            }
+        });
+        node.on_mouse_up({
+            let tx = tx.clone();
+            move |button, input_state, event| {
+                if button == MouseButton::Left {
+                    tx.send(TextInputEvent::MouseUp);
+                }
+            }
+        });
+        node.on_key_down({
+            let tx = tx.clone();
+            move |key, input_state, event| {
+                if handles_key(key, input_state.modifiers) {
+                    event.stop_propagation();
+                }
+                tx.send(TextInputEvent::KeyDown(key, input_state.modifiers));
+            }
+        });
+        node.on_char({
+            let tx = tx.clone();
+            move |c, input_state, event| {
+                if accepts_char(c, input_state.modifiers) {
+                    event.stop_propagation();
+                }
+                tx.send(TextInputEvent::Char(c, input_state.modifiers));
+            }
+        });
+        node.on_cut({
+            let tx = tx.clone();
+            move |input_state| {
+                tx.send(TextInputEvent::Cut);
+            }
+        });
+        node.on_paste(move |text, input_state| {
+            tx.send(TextInputEvent::Paste(text.to_string()));
+        });
+        node.set_selected_text(selected_text);
+
+        let background = if focused { style.focused_background } else { style.background };
+        node.set_shape(rounded_rect(Vec2::new(0.0, 0.0), Vec2::new(width, height), style.corner_radius, background));
+
+        let mut children = node.edit_children();
+        let view = children.add();
+        view.set_offset(padding, padding);
+        view.set_size(view_width, text_height);
+        view.set_clip(true);
+        let mut view_children = view.edit_children();
+
+        let highlight = view_children.add();
+        if focused && !selection.is_empty() {
+            let start = caret_x(&offsets, selection.start);
+            let end = caret_x(&offsets, selection.end);
+            highlight.set_shape(Shape::Rect {
+                position: Vec2::new(start - scroll, 0.0),
+                dimensions: Vec2::new(end - start, text_height),
+                color: style.selection_color,
+            });
+        } else {
+            highlight.set_shape(Shape::Empty);
+        }
+
+        let label = view_children.add();
+        let glyphs = font.layout(&text, size);
+        label.set_shape(Shape::Text {
+            position: Vec2::new(-scroll, 0.0),
+            font: font.clone(),
+            size,
+            advances: advances(&glyphs, text_width),
+            glyphs,
+            color: style.text_color,
+        });
+
+        let caret = view_children.add();
+        if focused {
+            caret.set_shape(Shape::Rect {
+                position: Vec2::new(cursor_x - scroll, 0.0),
+                dimensions: Vec2::new(1.0, text_height),
+                color: style.caret_color,
+            });
+        } else {
+            caret.set_shape(Shape::Empty);
+        }
+
+        drop(view_children);
+        drop(children);
+        node.set_size(width, height);
+        node.set_baseline(Some(padding + baseline(&font, size)));
+    }
+}
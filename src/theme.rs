@@ -0,0 +1,148 @@
+use std::rc::Rc;
+
+use gouache::{Color, Font};
+
+use crate::*;
+
+/// The colors, fonts and metrics used by the built-in widgets.
+///
+/// A theme applies to the subtree of a `Themed` element and is read by widgets during `apply`,
+/// so switching themes at runtime is just a matter of passing a different one. Outside of any
+/// `Themed` element, widgets use `Theme::dark()`. Individual widgets can override their part of
+/// the theme with their `style` method.
+#[derive(Clone)]
+pub struct Theme {
+    /// The font used by themed text and text inputs, e.g. `Text::themed`. The built-in themes
+    /// use the bundled `Theme::default_font`.
+    pub font: Rc<Font<'static>>,
+    pub text_size: f32,
+    pub text_color: Color,
+    pub button: ButtonStyle,
+    pub text_input: TextInputStyle,
+    pub scrollbar: ScrollbarStyle,
+}
+
+#[derive(Clone)]
+pub struct ButtonStyle {
+    pub color: Color,
+    pub hover_color: Color,
+    pub active_color: Color,
+    pub focus_color: Color,
+    pub padding: f32,
+    pub corner_radius: f32,
+}
+
+/// The style of `TextInput` and `TextArea`.
+#[derive(Clone)]
+pub struct TextInputStyle {
+    pub background: Color,
+    pub focused_background: Color,
+    pub text_color: Color,
+    pub selection_color: Color,
+    pub caret_color: Color,
+    pub padding: f32,
+    pub corner_radius: f32,
+}
+
+#[derive(Clone)]
+pub struct ScrollbarStyle {
+    pub track_color: Color,
+    pub thumb_color: Color,
+    pub thumb_hover_color: Color,
+    pub thumb_active_color: Color,
+    pub corner_radius: f32,
+}
+
+impl Theme {
+    pub fn dark() -> Theme {
+        let accent = Color::rgba(0.141, 0.44, 0.77, 1.0);
+        Theme {
+            font: Theme::default_font(),
+            text_size: 14.0,
+            text_color: Color::rgba(1.0, 1.0, 1.0, 1.0),
+            button: ButtonStyle {
+                color: Color::rgba(0.38, 0.42, 0.48, 1.0),
+                hover_color: Color::rgba(0.54, 0.63, 0.71, 1.0),
+                active_color: accent,
+                focus_color: accent,
+                padding: 5.0,
+                corner_radius: 0.0,
+            },
+            text_input: TextInputStyle {
+                background: Color::rgba(0.15, 0.18, 0.22, 1.0),
+                focused_background: Color::rgba(0.2, 0.24, 0.3, 1.0),
+                text_color: Color::rgba(1.0, 1.0, 1.0, 1.0),
+                selection_color: accent,
+                caret_color: Color::rgba(1.0, 1.0, 1.0, 1.0),
+                padding: 4.0,
+                corner_radius: 0.0,
+            },
+            scrollbar: ScrollbarStyle {
+                track_color: Color::rgba(0.0, 0.0, 0.0, 0.2),
+                thumb_color: Color::rgba(0.38, 0.42, 0.48, 1.0),
+                thumb_hover_color: Color::rgba(0.54, 0.63, 0.71, 1.0),
+                thumb_active_color: accent,
+                corner_radius: 0.0,
+            },
+        }
+    }
+
+    pub fn light() -> Theme {
+        let accent = Color::rgba(0.2, 0.5, 0.85, 1.0);
+        Theme {
+            font: Theme::default_font(),
+            text_size: 14.0,
+            text_color: Color::rgba(0.1, 0.1, 0.12, 1.0),
+            button: ButtonStyle {
+                color: Color::rgba(0.86, 0.87, 0.89, 1.0),
+                hover_color: Color::rgba(0.78, 0.81, 0.85, 1.0),
+                active_color: Color::rgba(0.62, 0.74, 0.88, 1.0),
+                focus_color: accent,
+                padding: 5.0,
+                corner_radius: 3.0,
+            },
+            text_input: TextInputStyle {
+                background: Color::rgba(0.95, 0.95, 0.96, 1.0),
+                focused_background: Color::rgba(1.0, 1.0, 1.0, 1.0),
+                text_color: Color::rgba(0.1, 0.1, 0.12, 1.0),
+                selection_color: Color::rgba(0.65, 0.8, 0.97, 1.0),
+                caret_color: Color::rgba(0.1, 0.1, 0.12, 1.0),
+                padding: 4.0,
+                corner_radius: 3.0,
+            },
+            scrollbar: ScrollbarStyle {
+                track_color: Color::rgba(0.0, 0.0, 0.0, 0.05),
+                thumb_color: Color::rgba(0.7, 0.72, 0.75, 1.0),
+                thumb_hover_color: Color::rgba(0.6, 0.62, 0.66, 1.0),
+                thumb_active_color: accent,
+                corner_radius: 5.0,
+            },
+        }
+    }
+
+    /// Returns the font bundled with casein, Source Sans Pro. It is loaded once per thread.
+    pub fn default_font() -> Rc<Font<'static>> {
+        DEFAULT_FONT.with(Rc::clone)
+    }
+
+    /// Returns the theme in effect for the element currently being applied.
+    pub fn current() -> Rc<Theme> {
+        context::<Theme>().unwrap_or_else(|| DEFAULT_THEME.with(Rc::clone))
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Theme {
+        Theme::dark()
+    }
+}
+
+thread_local! {
+    static DEFAULT_FONT: Rc<Font<'static>> = Rc::new(
+        Font::from_bytes(include_bytes!("../res/SourceSansPro-Regular.ttf")).expect("the bundled font is valid"),
+    );
+    static DEFAULT_THEME: Rc<Theme> = Rc::new(Theme::dark());
+}
+
+/// Applies a theme to everything inside its child, e.g. `Themed::new(Theme::light(), child)`.
+pub type Themed<C> = Provide<Theme, C>;